@@ -0,0 +1,163 @@
+use std::{
+    collections::HashMap,
+    fs,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::dns::DnsTransport;
+
+use super::ProcessInfo;
+
+/// Minimum time between scans of every process's open files
+const RESCAN_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The owner of each socket inode, as of the last scan of /proc
+#[derive(Default)]
+pub struct SocketOwners {
+    owners: HashMap<u64, ProcessInfo>,
+    last_scan: Option<Instant>,
+}
+
+impl SocketOwners {
+    /// The process owning the socket bound to `local`, rescanning processes
+    /// when the socket is new to us
+    pub fn find(
+        &mut self,
+        transport: DnsTransport,
+        local: SocketAddr,
+        now: Instant,
+    ) -> Option<ProcessInfo> {
+        // The socket may already be closed, e.g. after a one-off lookup
+        let inode = socket_inode(transport, local)?;
+        if !self.owners.contains_key(&inode)
+            && self
+                .last_scan
+                .is_none_or(|last| now.duration_since(last) >= RESCAN_INTERVAL)
+        {
+            self.owners = socket_owners();
+            self.last_scan = Some(now);
+        }
+        self.owners.get(&inode).cloned()
+    }
+}
+
+/// A socket from the kernel's table, keyed by its inode
+struct SocketEntry {
+    local: SocketAddr,
+    inode: u64,
+}
+
+/// Find the inode of the socket bound to `local`. A socket bound to the
+/// wildcard address on the same port is taken when none is bound to the
+/// address itself, as unconnected UDP sockets are.
+fn socket_inode(transport: DnsTransport, local: SocketAddr) -> Option<u64> {
+    let tables: &[&str] = match transport {
+        DnsTransport::Udp => &["/proc/net/udp", "/proc/net/udp6"],
+        DnsTransport::Tcp => &["/proc/net/tcp", "/proc/net/tcp6"],
+    };
+    let local_ip = local.ip().to_canonical();
+
+    let mut wildcard = None;
+    for table in tables {
+        let Ok(contents) = fs::read_to_string(table) else {
+            continue;
+        };
+        for entry in contents.lines().skip(1).filter_map(parse_socket_line) {
+            if entry.local.port() != local.port() {
+                continue;
+            }
+            let ip = entry.local.ip().to_canonical();
+            if ip == local_ip {
+                return Some(entry.inode);
+            }
+            if ip.is_unspecified() {
+                wildcard.get_or_insert(entry.inode);
+            }
+        }
+    }
+    wildcard
+}
+
+/// Parse a line of /proc/net/{udp,udp6,tcp,tcp6}, e.g.
+/// `0: 0100007F:0035 00000000:0000 07 00000000:00000000 00:00000000 00000000 0 0 12345 ...`
+fn parse_socket_line(line: &str) -> Option<SocketEntry> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let local = parse_address(fields.get(1)?)?;
+    // Sockets in TIME_WAIT have no owner and show inode 0
+    let inode = fields.get(9)?.parse().ok().filter(|inode| *inode != 0)?;
+    Some(SocketEntry { local, inode })
+}
+
+/// Parse an address written as hex words in host byte order and a hex port
+fn parse_address(field: &str) -> Option<SocketAddr> {
+    let (address, port) = field.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let mut bytes = Vec::with_capacity(16);
+    for i in (0..address.len()).step_by(8) {
+        let word = u32::from_str_radix(address.get(i..i + 8)?, 16).ok()?;
+        bytes.extend_from_slice(&word.to_ne_bytes());
+    }
+
+    let ip = match bytes.len() {
+        4 => IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(bytes).ok()?)),
+        16 => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?)),
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+/// Map the inode of every socket open in any process to that process, by
+/// reading the `socket:[inode]` links in /proc/*/fd
+fn socket_owners() -> HashMap<u64, ProcessInfo> {
+    let mut owners = HashMap::new();
+    let Ok(processes) = fs::read_dir("/proc") else {
+        return owners;
+    };
+
+    for entry in processes.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|pid| pid.parse().ok()) else {
+            continue;
+        };
+        // Processes may exit while being read, or belong to another user
+        let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+
+        let mut process = None;
+        for fd in fds.flatten() {
+            let Some(inode) = fs::read_link(fd.path()).ok().and_then(|target| {
+                target
+                    .to_str()?
+                    .strip_prefix("socket:[")?
+                    .strip_suffix(']')?
+                    .parse()
+                    .ok()
+            }) else {
+                continue;
+            };
+            let process = process.get_or_insert_with(|| ProcessInfo {
+                pid,
+                name: process_name(&entry.path()),
+            });
+            // A socket shared with a child keeps its first owner
+            owners.entry(inode).or_insert_with(|| process.clone());
+        }
+    }
+    owners
+}
+
+/// The file name of the process's executable, or its command name when that
+/// can't be read (e.g. kernel threads)
+fn process_name(proc_dir: &Path) -> String {
+    fs::read_link(proc_dir.join("exe"))
+        .ok()
+        .and_then(|exe| Some(exe.file_name()?.to_string_lossy().into_owned()))
+        .or_else(|| {
+            fs::read_to_string(proc_dir.join("comm"))
+                .ok()
+                .map(|comm| comm.trim_end().to_string())
+        })
+        .unwrap_or_else(|| "?".to_string())
+}
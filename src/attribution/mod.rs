@@ -0,0 +1,88 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    net::{IpAddr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use crate::dns::{DnsPacket, DnsTransport};
+
+#[cfg(target_os = "linux")]
+mod linux;
+
+/// How long a socket's owner is remembered. Kept short, as the port may be
+/// reused by another process once the socket closes.
+const SOCKET_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// A local process that sent a DNS query
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// Executable name, e.g. firefox
+    pub name: String,
+}
+
+impl fmt::Display for ProcessInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (pid {})", self.name, self.pid)
+    }
+}
+
+struct CachedSocket {
+    process: Option<ProcessInfo>,
+    expires: Instant,
+}
+
+/// Finds the process that owns the socket a query was sent from. Only Linux
+/// is supported, where sockets are looked up in /proc/net and matched to
+/// processes through /proc/*/fd; elsewhere no process is ever found.
+pub struct ProcessResolver {
+    /// Addresses of this host; queries from anywhere else aren't ours to attribute
+    local_addresses: Vec<IpAddr>,
+    sockets: HashMap<(DnsTransport, SocketAddr), CachedSocket>,
+    #[cfg(target_os = "linux")]
+    owners: linux::SocketOwners,
+}
+
+impl ProcessResolver {
+    pub fn new(local_addresses: Vec<IpAddr>) -> Self {
+        Self {
+            local_addresses,
+            sockets: HashMap::new(),
+            #[cfg(target_os = "linux")]
+            owners: linux::SocketOwners::default(),
+        }
+    }
+
+    /// The process that sent `packet`, if it's a query sent from this host
+    pub fn lookup(&mut self, packet: &DnsPacket) -> Option<ProcessInfo> {
+        if packet.header.is_response || !self.local_addresses.contains(&packet.source) {
+            return None;
+        }
+
+        let now = Instant::now();
+        let key = (
+            packet.transport,
+            SocketAddr::new(packet.source, packet.source_port),
+        );
+        if let Some(cached) = self.sockets.get(&key)
+            && cached.expires > now
+        {
+            return cached.process.clone();
+        }
+
+        self.sockets.retain(|_, cached| cached.expires > now);
+        #[cfg(target_os = "linux")]
+        let process = self.owners.find(key.0, key.1, now);
+        #[cfg(not(target_os = "linux"))]
+        let process = None;
+        self.sockets.insert(
+            key,
+            CachedSocket {
+                process: process.clone(),
+                expires: now + SOCKET_CACHE_TTL,
+            },
+        );
+        process
+    }
+}
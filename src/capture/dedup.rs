@@ -0,0 +1,79 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+    hash::{Hash, Hasher},
+    time::{Duration, SystemTime},
+};
+
+use pcap::Linktype;
+
+use crate::net;
+
+/// How long after a packet is seen a copy on another interface is dropped
+const DUPLICATE_WINDOW: Duration = Duration::from_millis(500);
+
+struct Seen {
+    interface: String,
+    timestamp: SystemTime,
+}
+
+/// Drops copies of a packet captured on more than one interface, such as a
+/// bridge and one of its veths. Packets are compared from the network layer
+/// on, as the link layers of the two captures may differ.
+#[derive(Default)]
+pub struct Deduplicator {
+    seen: HashMap<u64, Seen>,
+    /// Hashes in the order they were seen, for expiry
+    order: VecDeque<(SystemTime, u64)>,
+}
+
+impl Deduplicator {
+    /// Whether this packet was already seen on a different interface
+    pub fn is_duplicate(
+        &mut self,
+        interface: &str,
+        linktype: Linktype,
+        data: &[u8],
+        timestamp: SystemTime,
+    ) -> bool {
+        let Some(network) = net::strip_link_layer(linktype, data) else {
+            return false;
+        };
+        let mut hasher = DefaultHasher::new();
+        network.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        self.expire(timestamp);
+        if let Some(seen) = self.seen.get(&hash)
+            && seen.interface != interface
+        {
+            return true;
+        }
+
+        self.seen.insert(
+            hash,
+            Seen {
+                interface: interface.to_string(),
+                timestamp,
+            },
+        );
+        self.order.push_back((timestamp, hash));
+        false
+    }
+
+    fn expire(&mut self, now: SystemTime) {
+        while let Some((timestamp, hash)) = self.order.front().copied() {
+            if now.duration_since(timestamp).unwrap_or_default() < DUPLICATE_WINDOW {
+                break;
+            }
+            self.order.pop_front();
+            // A later sighting may have replaced the entry; only drop it if not
+            if self
+                .seen
+                .get(&hash)
+                .is_some_and(|seen| seen.timestamp == timestamp)
+            {
+                self.seen.remove(&hash);
+            }
+        }
+    }
+}
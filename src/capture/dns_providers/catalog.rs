@@ -0,0 +1,218 @@
+use std::{
+    env, fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::OnceCell;
+use ratatui::style::Color;
+use serde::Deserialize;
+
+use super::{trie::PrefixTrie, IpNetwork};
+
+/// Providers that are always available, see providers.toml
+const BUILTIN_CATALOG: &str = include_str!("providers.toml");
+
+/// Name reserved for traffic to resolvers that aren't in the catalog
+pub const UNKNOWN_PROVIDER: &str = "unknown";
+/// Name reserved for the resolvers this host is configured to use
+pub const SYSTEM_PROVIDER: &str = "system";
+
+/// Colors handed out to providers that don't set one
+const DEFAULT_COLORS: [Color; 6] = [
+    Color::LightBlue,
+    Color::LightGreen,
+    Color::LightMagenta,
+    Color::LightYellow,
+    Color::LightCyan,
+    Color::LightRed,
+];
+
+static CATALOG: OnceCell<ProviderCatalog> = OnceCell::new();
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CatalogFile {
+    #[serde(default, rename = "provider")]
+    providers: Vec<ProviderConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProviderConfig {
+    name: String,
+    color: Option<String>,
+    /// Addresses or CIDR ranges, IPv4 or IPv6
+    addresses: Vec<String>,
+    /// TLS server names of the provider's DNS-over-HTTPS endpoints
+    #[serde(default)]
+    doh_hosts: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ProviderEntry {
+    pub name: String,
+    pub color: Color,
+    pub networks: Vec<IpNetwork>,
+    /// Lowercased DoH server names
+    pub doh_hosts: Vec<String>,
+}
+
+/// Every known provider, indexed by `DnsProvider`. The first entry is always
+/// the unknown provider.
+#[derive(Debug)]
+pub struct ProviderCatalog {
+    pub providers: Vec<ProviderEntry>,
+    /// Every provider network, mapping to the provider's index
+    networks: PrefixTrie<usize>,
+}
+
+impl ProviderCatalog {
+    fn builtin() -> Self {
+        let mut catalog = Self {
+            providers: vec![ProviderEntry {
+                name: UNKNOWN_PROVIDER.to_string(),
+                color: Color::Gray,
+                networks: Vec::new(),
+                doh_hosts: Vec::new(),
+            }],
+            networks: PrefixTrie::default(),
+        };
+        catalog
+            .merge(BUILTIN_CATALOG)
+            .expect("built-in provider catalog is valid");
+        catalog
+    }
+
+    /// Add the providers defined in a TOML catalog, replacing any existing
+    /// provider with the same name
+    fn merge(&mut self, contents: &str) -> Result<()> {
+        let file: CatalogFile = toml::from_str(contents)?;
+
+        for config in file.providers {
+            let name = config.name.trim().to_string();
+            if name.is_empty() {
+                return Err(anyhow!("Provider names can't be empty"));
+            }
+            if name.eq_ignore_ascii_case(UNKNOWN_PROVIDER)
+                || name.eq_ignore_ascii_case(SYSTEM_PROVIDER)
+            {
+                return Err(anyhow!("'{}' is a reserved provider name", name));
+            }
+
+            let networks = config
+                .addresses
+                .iter()
+                .map(|address| address.parse::<IpNetwork>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| anyhow!("Provider '{}': {}", name, e))?;
+
+            let existing = self.position(&name);
+            let color = match &config.color {
+                Some(color) => color
+                    .parse::<Color>()
+                    .map_err(|_| anyhow!("Provider '{}': unknown color '{}'", name, color))?,
+                None => {
+                    let index = existing.unwrap_or(self.providers.len());
+                    DEFAULT_COLORS[index % DEFAULT_COLORS.len()]
+                }
+            };
+
+            let entry = ProviderEntry {
+                name,
+                color,
+                networks,
+                doh_hosts: config
+                    .doh_hosts
+                    .iter()
+                    .map(|host| host.trim().trim_end_matches('.').to_ascii_lowercase())
+                    .collect(),
+            };
+            match existing {
+                Some(index) => self.providers[index] = entry,
+                None => self.providers.push(entry),
+            }
+        }
+
+        self.rebuild_index();
+        Ok(())
+    }
+
+    /// Add the host's own resolvers as the system provider. Their exact
+    /// addresses take precedence over any provider range containing them.
+    fn add_system_resolvers(&mut self, resolvers: &[IpAddr]) {
+        if resolvers.is_empty() {
+            return;
+        }
+        self.providers.push(ProviderEntry {
+            name: SYSTEM_PROVIDER.to_string(),
+            color: Color::Blue,
+            networks: resolvers.iter().map(|ip| IpNetwork::from(*ip)).collect(),
+            doh_hosts: Vec::new(),
+        });
+        self.rebuild_index();
+    }
+
+    /// Rebuild the network trie. Where networks overlap the most specific one
+    /// wins, and for identical networks the provider defined last wins.
+    fn rebuild_index(&mut self) {
+        let mut networks = PrefixTrie::default();
+        for (index, provider) in self.providers.iter().enumerate() {
+            for network in &provider.networks {
+                networks.insert(*network, index);
+            }
+        }
+        self.networks = networks;
+    }
+
+    /// Index of the provider owning the most specific network containing `ip`
+    pub fn lookup(&self, ip: IpAddr) -> Option<usize> {
+        self.networks.longest_match(ip)
+    }
+
+    /// Index of the provider with this name, ignoring case
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.providers
+            .iter()
+            .position(|provider| provider.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// `$XDG_CONFIG_HOME/dustcloud/providers.toml`, falling back to `~/.config`
+pub fn default_catalog_path() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("dustcloud").join("providers.toml"))
+}
+
+/// Load the built-in providers plus those in `path`, or in the default
+/// catalog file if it exists, and label `system_resolvers` as the system
+/// provider. Must be called before any provider is looked up.
+pub fn load_catalog(path: Option<&Path>, system_resolvers: &[IpAddr]) -> Result<()> {
+    let mut catalog = ProviderCatalog::builtin();
+
+    let path = match path {
+        Some(path) => Some(path.to_path_buf()),
+        None => default_catalog_path().filter(|path| path.is_file()),
+    };
+    if let Some(path) = path {
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read provider catalog '{}'", path.display()))?;
+        catalog
+            .merge(&contents)
+            .with_context(|| format!("Invalid provider catalog '{}'", path.display()))?;
+    }
+    catalog.add_system_resolvers(system_resolvers);
+
+    CATALOG
+        .set(catalog)
+        .map_err(|_| anyhow!("Provider catalog already loaded"))
+}
+
+/// The loaded catalog, or just the built-in providers if none was loaded
+pub fn catalog() -> &'static ProviderCatalog {
+    CATALOG.get_or_init(ProviderCatalog::builtin)
+}
@@ -0,0 +1,138 @@
+use ratatui::style::Color;
+use std::net::IpAddr;
+
+mod catalog;
+mod network;
+mod system;
+mod trie;
+
+pub use catalog::load_catalog;
+use catalog::{catalog, SYSTEM_PROVIDER};
+pub use network::IpNetwork;
+pub use system::detect_system_resolvers;
+
+/// A provider from the catalog (built-in or user-defined), identified by its
+/// position in the catalog
+#[derive(Debug, Hash, Eq, PartialEq, Clone, Copy)]
+pub struct DnsProvider(usize);
+
+impl DnsProvider {
+    /// Traffic to or from a resolver that isn't in the catalog
+    pub const UNKNOWN: DnsProvider = DnsProvider(0);
+
+    pub fn as_str(&self) -> &'static str {
+        &catalog().providers[self.0].name
+    }
+
+    /// Color used for this provider in the TUI
+    pub fn color(&self) -> Color {
+        catalog().providers[self.0].color
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        catalog()
+            .position(s.trim())
+            .map_or(DnsProvider::UNKNOWN, DnsProvider)
+    }
+
+    /// The resolvers this host is configured to use, if any were detected
+    pub fn system() -> Option<DnsProvider> {
+        catalog().position(SYSTEM_PROVIDER).map(DnsProvider)
+    }
+
+    /// Every provider in the catalog, in the order they were defined
+    pub fn all() -> impl Iterator<Item = DnsProvider> {
+        (1..catalog().providers.len()).map(DnsProvider)
+    }
+
+    /// The provider with a DNS-over-HTTPS endpoint called `server_name`, or
+    /// with one that `server_name` is a subdomain of
+    pub fn for_doh_host(server_name: &str) -> Option<DnsProvider> {
+        let server_name = server_name.trim_end_matches('.');
+        DnsProvider::all().find(|provider| {
+            provider.doh_hosts().iter().any(|host| {
+                server_name == host
+                    || server_name
+                        .strip_suffix(host.as_str())
+                        .is_some_and(|prefix| prefix.ends_with('.'))
+            })
+        })
+    }
+
+    /// Whether `ip` is in one of this provider's networks
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.networks().iter().any(|network| network.contains(ip))
+    }
+
+    fn networks(&self) -> &'static [IpNetwork] {
+        &catalog().providers[self.0].networks
+    }
+
+    fn doh_hosts(&self) -> &'static [String] {
+        &catalog().providers[self.0].doh_hosts
+    }
+}
+
+/// The provider whose most specific network contains `ip`
+pub fn get_provider_for_ip(ip: IpAddr) -> DnsProvider {
+    catalog()
+        .lookup(ip)
+        .map_or(DnsProvider::UNKNOWN, DnsProvider)
+}
+
+/// Addresses of the detected system resolvers
+pub fn system_resolvers() -> Vec<IpAddr> {
+    DnsProvider::system()
+        .map(|system| system.networks().iter().map(|net| net.address()).collect())
+        .unwrap_or_default()
+}
+
+/// Ports carrying DNS in the clear (53) and over TLS or QUIC (853)
+pub const DNS_PORTS_FILTER: &str = "port 53 or port 853";
+
+pub fn get_filter_for_providers(providers: &[DnsProvider]) -> String {
+    if providers.is_empty() {
+        return DNS_PORTS_FILTER.to_string(); // Default to all DNS traffic if no providers specified
+    }
+
+    // Create the filter conditions from every network of the requested providers
+    let ip_conditions = host_conditions(providers.iter().flat_map(|provider| provider.networks()));
+    let filter = format!("({}) and ({})", DNS_PORTS_FILTER, ip_conditions);
+
+    match get_doh_filter(providers) {
+        Some(doh) => format!("{} or {}", filter, doh),
+        None => filter,
+    }
+}
+
+/// Filter for HTTPS traffic to the networks of those `providers` that run
+/// DNS-over-HTTPS endpoints, if any do
+pub fn get_doh_filter(providers: &[DnsProvider]) -> Option<String> {
+    let networks: Vec<&IpNetwork> = providers
+        .iter()
+        .filter(|provider| !provider.doh_hosts().is_empty())
+        .flat_map(|provider| provider.networks())
+        .collect();
+    if networks.is_empty() {
+        return None;
+    }
+
+    Some(format!(
+        "(tcp port 443 and ({}))",
+        host_conditions(networks.into_iter())
+    ))
+}
+
+fn host_conditions<'a>(networks: impl Iterator<Item = &'a IpNetwork>) -> String {
+    networks
+        .map(|network| network.bpf_expression())
+        .collect::<Vec<_>>()
+        .join(" or ")
+}
+
+// Get a comma-separated list of all DNS providers
+pub fn list_all_providers() -> String {
+    let providers: Vec<&str> = DnsProvider::all().map(|p| p.as_str()).collect();
+
+    providers.join(", ")
+}
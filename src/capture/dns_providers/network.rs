@@ -0,0 +1,112 @@
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+/// An IPv4 or IPv6 network in CIDR notation. A bare address is a /32 or /128.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNetwork {
+    pub fn address(&self) -> IpAddr {
+        self.address
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Whether this network is a single address
+    pub fn is_host(&self) -> bool {
+        self.prefix_len == max_prefix_len(self.address)
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.address, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => mask_v4(ip, self.prefix_len) == network,
+            (IpAddr::V6(network), IpAddr::V6(ip)) => mask_v6(ip, self.prefix_len) == network,
+            _ => false,
+        }
+    }
+
+    /// The pcap filter expression matching traffic to or from this network
+    pub fn bpf_expression(&self) -> String {
+        match (self.address, self.is_host()) {
+            (IpAddr::V4(_), true) => format!("ip host {}", self.address),
+            (IpAddr::V6(_), true) => format!("ip6 host {}", self.address),
+            (_, false) => format!("net {}", self),
+        }
+    }
+}
+
+impl From<IpAddr> for IpNetwork {
+    fn from(address: IpAddr) -> Self {
+        Self {
+            address,
+            prefix_len: max_prefix_len(address),
+        }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix_len) = match s.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (s, None),
+        };
+
+        let address: IpAddr = address
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid IP address '{}'", address))?;
+        let max = max_prefix_len(address);
+        let prefix_len = match prefix_len {
+            Some(len) => len
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max)
+                .ok_or_else(|| format!("invalid prefix length in '{}'", s))?,
+            None => max,
+        };
+
+        // Store the network address so that e.g. 1.1.1.1/24 means 1.1.1.0/24
+        let address = match address {
+            IpAddr::V4(v4) => IpAddr::V4(mask_v4(v4, prefix_len)),
+            IpAddr::V6(v6) => IpAddr::V6(mask_v6(v6, prefix_len)),
+        };
+        Ok(Self {
+            address,
+            prefix_len,
+        })
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+fn max_prefix_len(address: IpAddr) -> u8 {
+    match address {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+fn mask_v4(ip: Ipv4Addr, prefix_len: u8) -> Ipv4Addr {
+    let mask = u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0);
+    Ipv4Addr::from(u32::from(ip) & mask)
+}
+
+fn mask_v6(ip: Ipv6Addr, prefix_len: u8) -> Ipv6Addr {
+    let mask = u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0);
+    Ipv6Addr::from(u128::from(ip) & mask)
+}
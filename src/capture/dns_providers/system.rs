@@ -0,0 +1,98 @@
+use std::{fs, net::IpAddr};
+
+/// Find the resolvers this host is configured to use: the nameservers in
+/// /etc/resolv.conf plus, where a local stub forwards elsewhere, the upstream
+/// servers behind it. Sources that aren't available are skipped.
+pub fn detect_system_resolvers() -> Vec<IpAddr> {
+    let mut resolvers = read_resolv_conf("/etc/resolv.conf");
+
+    #[cfg(target_os = "linux")]
+    {
+        // systemd-resolved points resolv.conf at its stub (127.0.0.53); the
+        // servers it forwards to are listed here and per link by resolvectl
+        resolvers.extend(read_resolv_conf("/run/systemd/resolve/resolv.conf"));
+        resolvers.extend(resolvectl_servers());
+    }
+
+    #[cfg(target_os = "macos")]
+    resolvers.extend(scutil_servers());
+
+    let mut unique = Vec::new();
+    for resolver in resolvers {
+        if !unique.contains(&resolver) {
+            unique.push(resolver);
+        }
+    }
+    unique
+}
+
+fn read_resolv_conf(path: &str) -> Vec<IpAddr> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("nameserver") => fields.next().and_then(parse_server),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Parse `resolvectl dns` output, e.g.
+/// ```text
+/// Global: 1.1.1.1#cloudflare-dns.com
+/// Link 2 (eth0): 192.168.1.1 fe80::1%eth0
+/// ```
+#[cfg(target_os = "linux")]
+fn resolvectl_servers() -> Vec<IpAddr> {
+    let Some(output) = run("resolvectl", &["dns"]) else {
+        return Vec::new();
+    };
+
+    output
+        .lines()
+        .filter_map(|line| {
+            line.strip_prefix("Global:")
+                .or_else(|| line.split_once("): ").map(|(_, servers)| servers))
+        })
+        .flat_map(|servers| servers.split_whitespace().filter_map(parse_server))
+        .collect()
+}
+
+/// Parse `scutil --dns` output, which lists each resolver's servers as
+/// `nameserver[0] : 192.168.1.1`
+#[cfg(target_os = "macos")]
+fn scutil_servers() -> Vec<IpAddr> {
+    let Some(output) = run("scutil", &["--dns"]) else {
+        return Vec::new();
+    };
+
+    output
+        .lines()
+        .filter(|line| line.trim_start().starts_with("nameserver["))
+        .filter_map(|line| line.split_once(" : "))
+        .filter_map(|(_, server)| parse_server(server.trim()))
+        .collect()
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Parse a server address, dropping any `%interface` scope or `#server-name`
+fn parse_server(server: &str) -> Option<IpAddr> {
+    server.split(['%', '#']).next()?.parse().ok()
+}
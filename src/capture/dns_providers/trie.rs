@@ -0,0 +1,97 @@
+use std::net::IpAddr;
+
+use super::IpNetwork;
+
+/// Index of the root node, which is never anyone's child, so 0 doubles as "no child"
+const ROOT: u32 = 0;
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    children: [u32; 2],
+    value: Option<T>,
+}
+
+impl<T> Node<T> {
+    fn new() -> Self {
+        Self {
+            children: [ROOT; 2],
+            value: None,
+        }
+    }
+}
+
+/// Binary trie over address bits with separate IPv4 and IPv6 roots, giving
+/// longest-prefix matching in at most 32 or 128 steps without allocating.
+/// Nodes live in a flat arena and refer to each other by index.
+#[derive(Debug, Clone)]
+pub struct PrefixTrie<T> {
+    v4: Vec<Node<T>>,
+    v6: Vec<Node<T>>,
+}
+
+impl<T: Copy> Default for PrefixTrie<T> {
+    fn default() -> Self {
+        Self {
+            v4: vec![Node::new()],
+            v6: vec![Node::new()],
+        }
+    }
+}
+
+impl<T: Copy> PrefixTrie<T> {
+    /// Associate `value` with `network`, replacing any value for the same prefix
+    pub fn insert(&mut self, network: IpNetwork, value: T) {
+        let (nodes, bits) = self.nodes_mut(network.address());
+
+        let mut index = ROOT;
+        for depth in 0..network.prefix_len() {
+            let bit = bit_at(bits, depth);
+            let child = nodes[index as usize].children[bit];
+            index = if child == ROOT {
+                nodes.push(Node::new());
+                let child = (nodes.len() - 1) as u32;
+                nodes[index as usize].children[bit] = child;
+                child
+            } else {
+                child
+            };
+        }
+        nodes[index as usize].value = Some(value);
+    }
+
+    /// The value of the most specific network containing `ip`
+    pub fn longest_match(&self, ip: IpAddr) -> Option<T> {
+        // Treat IPv4-mapped IPv6 addresses (::ffff:a.b.c.d) as IPv4
+        let ip = ip.to_canonical();
+        let (nodes, bits, width) = match ip {
+            IpAddr::V4(v4) => (&self.v4, (u32::from(v4) as u128) << 96, 32),
+            IpAddr::V6(v6) => (&self.v6, u128::from(v6), 128),
+        };
+
+        let mut index = ROOT;
+        let mut best = nodes[ROOT as usize].value;
+        for depth in 0..width {
+            index = nodes[index as usize].children[bit_at(bits, depth)];
+            if index == ROOT {
+                break;
+            }
+            if let Some(value) = nodes[index as usize].value {
+                best = Some(value);
+            }
+        }
+        best
+    }
+
+    fn nodes_mut(&mut self, address: IpAddr) -> (&mut Vec<Node<T>>, u128) {
+        match address {
+            IpAddr::V4(v4) => (&mut self.v4, (u32::from(v4) as u128) << 96),
+            IpAddr::V6(v6) => (&mut self.v6, u128::from(v6)),
+        }
+    }
+}
+
+/// The bit at `depth` counting from the most significant, of an address that
+/// has been left-aligned in a u128
+fn bit_at(bits: u128, depth: u8) -> usize {
+    ((bits >> (127 - depth as u32)) & 1) as usize
+}
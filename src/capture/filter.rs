@@ -1,14 +1,65 @@
-use crate::cli::Args;
+use anyhow::{anyhow, Result};
+use pcap::{Capture, Linktype};
 
-use super::dns_providers::get_filter_for_providers;
+use crate::{cli::Args, dns::DnsProtocol};
 
-// TODO(RC): make way more generic to allow passing arbitrary data types through this
+use super::dns_providers::{
+    get_doh_filter, get_filter_for_providers, DnsProvider, DNS_PORTS_FILTER,
+};
+
+/// The BPF program applied to every capture: the parts for each protocol,
+/// narrowed by the user's own `--bpf` expression if one was given
 pub fn build_capture_filter(args: &Args) -> String {
+    let filter = protocols_filter(args);
+    match &args.bpf {
+        Some(bpf) => format!("({}) and ({})", filter, bpf),
+        None => filter,
+    }
+}
+
+/// Check that the user's `--bpf` expression compiles, so a mistake in it is
+/// reported as such rather than as every device failing to open
+pub fn check_bpf(args: &Args) -> Result<()> {
+    let Some(bpf) = &args.bpf else {
+        return Ok(());
+    };
+    Capture::dead(Linktype::ETHERNET)?
+        .compile(bpf, false)
+        .map_err(|e| anyhow!("Invalid --bpf expression '{}': {}", bpf, e))?;
+    Ok(())
+}
+
+fn protocols_filter(args: &Args) -> String {
+    let filters: Vec<String> = args
+        .get_protocols()
+        .iter()
+        .map(|protocol| match protocol {
+            DnsProtocol::Dns => dns_filter(args),
+            protocol => format!("udp port {}", protocol.port()),
+        })
+        .collect();
+
+    match filters.as_slice() {
+        [filter] => filter.clone(),
+        // BPF gives `and` and `or` the same precedence, so each part needs brackets
+        filters => filters
+            .iter()
+            .map(|filter| format!("({})", filter))
+            .collect::<Vec<_>>()
+            .join(" or "),
+    }
+}
+
+fn dns_filter(args: &Args) -> String {
     let providers = args.get_dns_providers();
     if !providers.is_empty() {
         get_filter_for_providers(&providers)
     } else {
-        // Filter for all DNS traffic
-        "udp port 53 or tcp port 53".to_string()
+        // Filter for all DNS traffic, plus HTTPS to any known DoH endpoint
+        let providers: Vec<DnsProvider> = DnsProvider::all().collect();
+        match get_doh_filter(&providers) {
+            Some(doh) => format!("{} or {}", DNS_PORTS_FILTER, doh),
+            None => DNS_PORTS_FILTER.to_string(),
+        }
     }
 }
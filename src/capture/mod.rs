@@ -1,44 +1,165 @@
-use crate::capture::filter::build_capture_filter;
-use crate::cli::Args;
-use crate::dns;
+use crate::attribution::ProcessResolver;
+use crate::capture::filter::{build_capture_filter, check_bpf};
+use crate::cli::{Args, OutputFormat};
+use crate::dns::{packet_timestamp, Correlator, DnsDecoder, DnsEvent, DnsPacket};
+use crate::privileges::{is_permission_error, permission_help};
+use crate::shutdown;
 use anyhow::{anyhow, Context, Result};
-use output_mode::{ChannelOutput, CliOutput, PacketHandler, Tx};
-use pcap::{Capture, Device};
-use std::time::Duration;
+use dedup::Deduplicator;
+use output_mode::{ChannelOutput, CliOutput, JsonOutput, PacketHandler, Tx};
+use pcap::{Activated, Capture, Device, Linktype, Packet, PacketHeader};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    net::IpAddr,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender},
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+pub use summary::CaptureSummary;
+use summary::SourceStats;
+use writer::PcapWriter;
 
+mod dedup;
 pub mod dns_providers;
 mod filter;
 mod output_mode;
+mod summary;
+mod writer;
 
-pub fn start_capture_with_channel(args: &Args, tx: Tx) -> Result<()> {
-    run_capture(args, ChannelOutput(tx))
+/// How long to wait when there are no packets, both in the capture threads
+/// and the processing loop
+const IDLE_INTERVAL: Duration = Duration::from_millis(100);
+/// Packets buffered between the capture threads and the processing loop.
+/// Bounded so a savefile isn't read into memory faster than it's processed.
+const CHANNEL_CAPACITY: usize = 4096;
+
+/// An opened capture and the interface it's on (None for a savefile)
+struct Source {
+    interface: Option<String>,
+    capture: Capture<dyn Activated>,
+}
+
+/// A packet copied out of its capture so it can be passed to the processing loop
+struct CapturedPacket {
+    interface: Option<String>,
+    linktype: Linktype,
+    header: PacketHeader,
+    data: Vec<u8>,
+}
+
+/// The savefile or devices to capture from, opened before privileges are dropped
+pub struct Captures(Vec<Source>);
+
+enum CaptureMessage {
+    Packet(CapturedPacket),
+    /// A capture stopped on an error
+    Failed(anyhow::Error),
 }
 
-pub fn start_capture(args: &Args) -> Result<()> {
-    run_capture(args, CliOutput)
+pub fn start_capture_with_channel(
+    args: &Args,
+    captures: Captures,
+    tx: Tx,
+) -> Result<CaptureSummary> {
+    run_capture(args, captures, ChannelOutput(tx))
 }
 
-fn get_selected_device(args: &Args, devices: Vec<Device>) -> Result<Device, anyhow::Error> {
-    let device = match &args.device {
-        Some(name) => devices
+pub fn start_capture(args: &Args, captures: Captures) -> Result<CaptureSummary> {
+    match args.output_format {
+        OutputFormat::Text => run_capture(args, captures, CliOutput),
+        OutputFormat::Json => run_capture(args, captures, JsonOutput::array()),
+        OutputFormat::Ndjson => run_capture(args, captures, JsonOutput::lines()),
+    }
+}
+
+fn get_selected_devices(args: &Args, devices: Vec<Device>) -> Result<Vec<Device>> {
+    if args.captures_all_devices() {
+        // Linux's "any" pseudo-device would see every packet a second time
+        let up: Vec<Device> = devices
             .into_iter()
-            .find(|d| d.name == *name)
-            .ok_or_else(|| anyhow!("Device '{}' not found", name))?,
-        None => {
-            let default = Device::lookup()?.ok_or_else(|| anyhow!("No default device found"))?;
-            if args.verbose {
-                println!("Selected default device: {}", default.name);
-            }
-            default
+            .filter(|d| d.flags.is_up() && d.flags.is_running() && d.name != "any")
+            .collect();
+        if up.is_empty() {
+            return Err(anyhow!("No network devices are up"));
         }
-    };
-    Ok(device)
+        return Ok(up);
+    }
+
+    if args.devices.is_empty() {
+        let default = Device::lookup()?.ok_or_else(|| anyhow!("No default device found"))?;
+        if args.verbose {
+            println!("Selected default device: {}", default.name);
+        }
+        return Ok(vec![default]);
+    }
+
+    let mut selected: Vec<Device> = Vec::new();
+    for name in &args.devices {
+        let device = devices
+            .iter()
+            .find(|d| d.name == *name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Device '{}' not found", name))?;
+        selected.push(device);
+    }
+    Ok(selected)
 }
 
-fn run_capture<C>(args: &Args, capture_mode: C) -> Result<()>
+/// Capture until every source has ended or a shutdown is requested
+fn run_capture<C>(args: &Args, captures: Captures, capture_mode: C) -> Result<CaptureSummary>
 where
     C: PacketHandler,
 {
+    let Captures(sources) = captures;
+    let multiple = sources.len() > 1;
+
+    // One thread per capture, all feeding the same processing loop
+    let (tx, rx) = mpsc::sync_channel(CHANNEL_CAPACITY);
+    let readers: Vec<JoinHandle<Option<SourceStats>>> = sources
+        .into_iter()
+        .map(|source| {
+            let tx = tx.clone();
+            let continue_on_error = args.continue_on_error;
+            thread::spawn(move || read_packets(source, continue_on_error, tx))
+        })
+        .collect();
+    drop(tx);
+
+    let result = process_packets(rx, args, &capture_mode, multiple);
+    capture_mode.finish();
+    if result.is_err() {
+        // Stop the other captures, and whatever is showing their packets
+        shutdown::request();
+    }
+    let sources = readers
+        .into_iter()
+        .filter_map(|reader| reader.join().ok().flatten())
+        .collect();
+    Ok(CaptureSummary { sources, ..result? })
+}
+
+/// Open the savefile or devices to capture from, with the filter applied
+pub fn open_captures(args: &Args) -> Result<Captures> {
+    check_bpf(args)?;
+    let filter = build_capture_filter(args);
+    if args.verbose {
+        println!("Using filter: {}", filter);
+    }
+
+    if let Some(path) = &args.read {
+        let mut cap = Capture::from_file(path)
+            .with_context(|| format!("Failed to open capture file '{}'", path.display()))?;
+        if args.verbose {
+            println!("Reading packets from: {}", path.display());
+        }
+        cap.filter(&filter, true)?;
+        print_linktype(args, path.to_string_lossy().as_ref(), cap.get_datalink());
+        return Ok(Captures(vec![Source {
+            interface: None,
+            capture: cap.into(),
+        }]));
+    }
+
     let devices = Device::list().context("Failed to list network devices")?;
     if args.verbose {
         println!("Available devices:");
@@ -52,40 +173,298 @@ where
         }
     }
 
-    let device = get_selected_device(args, devices)?;
+    let mut sources = Vec::new();
+    let mut permission_denied = None;
+    for device in get_selected_devices(args, devices)? {
+        let name = device.name.clone();
+        match open_device(device, &filter) {
+            Ok(capture) => {
+                print_linktype(args, &name, capture.get_datalink());
+                sources.push(Source {
+                    interface: Some(name),
+                    capture,
+                });
+            }
+            // Some interfaces can't be captured on (no permission, no BPF
+            // support); with "all" those are skipped rather than fatal
+            Err(e) if args.captures_all_devices() => {
+                if args.verbose {
+                    println!("Skipping device {}: {}", name, e);
+                }
+                if is_permission_error(&e) {
+                    permission_denied.get_or_insert((name, e));
+                }
+            }
+            Err(e) => return Err(open_error(&name, e)),
+        }
+    }
+    if sources.is_empty() {
+        return Err(match permission_denied {
+            Some((name, e)) => open_error(&name, e),
+            None => anyhow!("None of the network devices could be opened"),
+        });
+    }
+    Ok(Captures(sources))
+}
+
+/// Describe why a device couldn't be opened, with advice when it's for lack
+/// of privileges
+fn open_error(device: &str, error: pcap::Error) -> anyhow::Error {
+    if is_permission_error(&error) {
+        anyhow!(
+            "No permission to capture on '{}': {}\n{}",
+            device,
+            error,
+            permission_help()
+        )
+    } else {
+        anyhow!("Failed to open device '{}': {}", device, error)
+    }
+}
+
+fn open_device(device: Device, filter: &str) -> Result<Capture<dyn Activated>, pcap::Error> {
     let mut cap = Capture::from_device(device)?
         .promisc(true) // Promiscuous mode to capture all packets
         .snaplen(65535) // Maximum packet size
         .timeout(1000) // Milliseconds
         .open()?
         .setnonblock()?;
-    let filter = build_capture_filter(args);
+    cap.filter(filter, true)?;
+    Ok(cap.into())
+}
+
+fn print_linktype(args: &Args, source: &str, linktype: Linktype) {
     if args.verbose {
-        println!("Using filter: {}", filter);
+        println!(
+            "Link type of {}: {}",
+            source,
+            linktype
+                .get_name()
+                .unwrap_or_else(|_| linktype.0.to_string())
+        );
     }
-    cap.filter(&filter, true)?;
+}
+
+/// Copy packets from one capture into the channel until it ends, fails or a
+/// shutdown is requested. Returns pcap's counters for a live capture.
+fn read_packets(
+    mut source: Source,
+    continue_on_error: bool,
+    tx: SyncSender<CaptureMessage>,
+) -> Option<SourceStats> {
+    let linktype = source.capture.get_datalink();
+    let name = source
+        .interface
+        .clone()
+        .unwrap_or_else(|| "capture file".to_string());
+
+    while !shutdown::requested() {
+        let message = match source.capture.next_packet() {
+            Ok(packet) => CaptureMessage::Packet(CapturedPacket {
+                interface: source.interface.clone(),
+                linktype,
+                header: *packet.header,
+                data: packet.data.to_vec(),
+            }),
+            Err(pcap::Error::TimeoutExpired) => {
+                // This is normal with nonblocking mode
+                thread::sleep(IDLE_INTERVAL);
+                continue;
+            }
+            // End of a savefile
+            Err(pcap::Error::NoMorePackets) => break,
+            Err(e) => {
+                eprintln!("Error capturing packet on {}: {}", name, e);
+                if continue_on_error {
+                    continue;
+                }
+                CaptureMessage::Failed(anyhow!("Capture error on {}: {}", name, e))
+            }
+        };
+
+        let failed = matches!(message, CaptureMessage::Failed(_));
+        if tx.send(message).is_err() || failed {
+            break;
+        }
+    }
+
+    // Savefiles keep no counters
+    let interface = source.interface?;
+    match source.capture.stats() {
+        Ok(stats) => Some(SourceStats {
+            name: interface,
+            received: stats.received,
+            dropped: stats.dropped,
+            if_dropped: stats.if_dropped,
+        }),
+        Err(e) => {
+            eprintln!("Failed to get capture statistics for {}: {}", interface, e);
+            None
+        }
+    }
+}
+
+/// Feed every packet from the captures to the handler. Returns once every
+/// capture has ended, i.e. a savefile has been read to the end or a shutdown
+/// was requested, with counts of what was seen.
+fn process_packets<C>(
+    rx: Receiver<CaptureMessage>,
+    args: &Args,
+    capture_mode: &C,
+    multiple: bool,
+) -> Result<CaptureSummary>
+where
+    C: PacketHandler,
+{
+    // One savefile per interface when there are several, as their link types may differ
+    let mut writers: HashMap<Option<String>, PcapWriter> = HashMap::new();
+    let mut dedup = multiple.then(Deduplicator::default);
+    let mut decoder = DnsDecoder::new();
+    let mut correlator = Correlator::new(args.query_timeout);
+    // Only live traffic can be traced back to the processes that sent it
+    let mut processes = args
+        .read
+        .is_none()
+        .then(|| ProcessResolver::new(local_addresses()));
+    let mut summary = CaptureSummary::default();
     loop {
-        match cap.next_packet() {
-            Ok(packet) => {
+        match rx.recv_timeout(IDLE_INTERVAL) {
+            Ok(CaptureMessage::Packet(captured)) => {
+                summary.packets += 1;
+                let packet = Packet::new(&captured.header, &captured.data);
+                let interface = captured.interface.as_deref();
+
+                if let Entry::Vacant(entry) = writers.entry(captured.interface.clone())
+                    && let Some(writer) = PcapWriter::from_args(
+                        args,
+                        captured.linktype,
+                        interface.filter(|_| multiple),
+                    )?
+                {
+                    entry.insert(writer);
+                }
+                if let Some(writer) = writers.get_mut(&captured.interface) {
+                    writer.write(&packet)?;
+                }
+
+                if let (Some(dedup), Some(interface)) = (&mut dedup, interface)
+                    && dedup.is_duplicate(
+                        interface,
+                        captured.linktype,
+                        &captured.data,
+                        packet_timestamp(&captured.header),
+                    )
+                {
+                    summary.duplicates += 1;
+                    continue;
+                }
+
                 // TODO: handle more than just dns packets
-                if let Some(dns_packet) = dns::parse_packet(&packet) {
-                    capture_mode.handle_dns_packet(dns_packet, args);
+                let events = decoder.parse_packet(&packet, captured.linktype, interface);
+                if !events.is_empty() {
+                    summary.parsed += 1;
+                }
+                for event in events {
+                    let mut dns_packet = match event {
+                        DnsEvent::Message(dns_packet) => dns_packet,
+                        DnsEvent::Encrypted(update) => {
+                            capture_mode.handle_encrypted_dns(update, args);
+                            continue;
+                        }
+                    };
+                    summary.messages += 1;
+                    if let Some(processes) = &mut processes {
+                        dns_packet.process = processes.lookup(&dns_packet);
+                    }
+                    // Packet timestamps drive expiry so savefiles time out queries
+                    // as they would have live
+                    for query in correlator
+                        .expire(dns_packet.timestamp)
+                        .into_iter()
+                        .filter(|query| shown(args, query))
+                    {
+                        capture_mode.handle_timeout(query, args);
+                    }
+                    // Every message is correlated, so that a response shown
+                    // on its own still has its latency measured
+                    let transaction = correlator.observe(&dns_packet);
+                    if shown(args, &dns_packet)
+                        && (transaction.is_none() || capture_mode.wants_matched_responses())
+                    {
+                        capture_mode.handle_dns_packet(dns_packet, args);
+                    }
+                    if let Some(transaction) = transaction
+                        && (shown(args, &transaction.query) || shown(args, &transaction.response))
+                    {
+                        capture_mode.handle_transaction(transaction, args);
+                    }
                 }
             }
-            Err(pcap::Error::TimeoutExpired) => {
-                // This is normal with nonblocking mode
-                std::thread::sleep(Duration::from_millis(100));
+            Ok(CaptureMessage::Failed(e)) => return Err(e),
+            Err(RecvTimeoutError::Timeout) => {
+                for writer in writers.values_mut() {
+                    writer.flush()?;
+                }
+                // A savefile is timed by its packets alone; a pause in reading
+                // it mustn't time out its queries
+                if args.read.is_none() {
+                    for query in correlator
+                        .expire(SystemTime::now())
+                        .into_iter()
+                        .filter(|query| shown(args, query))
+                    {
+                        capture_mode.handle_timeout(query, args);
+                    }
+                    for update in decoder.expire_sessions(SystemTime::now()) {
+                        capture_mode.handle_encrypted_dns(update, args);
+                    }
+                }
             }
-            Err(e) => {
-                eprintln!("Error capturing packet: {}", e);
-                if !args.continue_on_error {
-                    return Err(anyhow!("Capture error: {}", e));
+            Err(RecvTimeoutError::Disconnected) => {
+                for writer in writers.values_mut() {
+                    writer.flush()?;
+                }
+                if shutdown::requested() {
+                    // Stopped early: these may still be answered, so they
+                    // aren't reported as timeouts
+                    summary.pending = correlator.drain().len();
+                } else {
+                    // Every capture has ended: anything still outstanding was never answered
+                    for query in correlator
+                        .drain()
+                        .into_iter()
+                        .filter(|query| shown(args, query))
+                    {
+                        capture_mode.handle_timeout(query, args);
+                    }
                 }
+                for update in decoder.close_sessions() {
+                    capture_mode.handle_encrypted_dns(update, args);
+                }
+                return Ok(summary);
             }
         }
     }
 }
 
+/// Addresses of every local interface, to tell queries sent by this host
+/// from ones it only sees
+fn local_addresses() -> Vec<IpAddr> {
+    Device::list()
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|device| device.addresses)
+        .map(|address| address.addr)
+        .collect()
+}
+
+/// Whether a message passes the `--filter` expression, if there is one
+fn shown(args: &Args, packet: &DnsPacket) -> bool {
+    args.filter
+        .as_ref()
+        .is_none_or(|filter| filter.matches(packet))
+}
+
 /// Get available network devices
 pub fn list_devices() -> Result<Vec<Device>> {
     Device::list().context("Failed to list network devices")
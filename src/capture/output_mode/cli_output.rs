@@ -1,31 +1,243 @@
-use chrono::DateTime;
+use std::net::SocketAddr;
+
+use chrono::{DateTime, Local};
+
+use crate::{
+    dns::{DnsPacket, DnsTransaction, EncryptedDnsUpdate, SessionEvent},
+    net::ChecksumStatus,
+};
 
 use super::PacketHandler;
 
 pub struct CliOutput;
 
+/// Names the interface a packet was seen on, when capturing on several
+fn interface_tag(interface: Option<&str>, args: &crate::cli::Args) -> String {
+    match interface {
+        Some(interface) if args.captures_multiple_devices() => format!("[{}] ", interface),
+        _ => String::new(),
+    }
+}
+
+/// Who a message was exchanged with: the provider for DNS, otherwise the
+/// address, as link-local protocols have no resolver
+fn responder(packet: &DnsPacket) -> String {
+    if packet.protocol.is_unicast() {
+        packet.provider.as_str().to_string()
+    } else if packet.header.is_response {
+        packet.source.to_string()
+    } else {
+        packet.destination.to_string()
+    }
+}
+
 impl PacketHandler for CliOutput {
-    fn handle_dns_packet(&self, dns_packet: crate::dns::DnsPacket, args: &crate::cli::Args) {
-        if let Some(query) = dns_packet.query {
+    fn handle_dns_packet(&self, dns_packet: DnsPacket, args: &crate::cli::Args) {
+        let time = DateTime::<Local>::from(dns_packet.timestamp).format("%Y-%m-%d %H:%M:%S%.3f");
+        let header = &dns_packet.header;
+        let tag = interface_tag(dns_packet.interface.as_deref(), args);
+
+        if !header.is_response {
+            // The client asked for DNSSEC records along with the answer
+            let dnssec_ok = dns_packet.edns.as_ref().is_some_and(|edns| edns.dnssec_ok);
+            for query in &dns_packet.questions {
+                let mut flags = String::new();
+                if query.unicast_response {
+                    flags.push_str(", QU");
+                }
+                if dnssec_ok {
+                    flags.push_str(", DO");
+                }
+                let process = dns_packet
+                    .process
+                    .as_ref()
+                    .map(|process| format!(" by {}", process))
+                    .unwrap_or_default();
+                if dns_packet.protocol.is_unicast() {
+                    println!(
+                        "[{}] {}{} Query: {} (Type: {}{}){} -> Estimated Provider: {}{}",
+                        time,
+                        tag,
+                        dns_packet.protocol,
+                        query.name,
+                        query.query_type,
+                        flags,
+                        process,
+                        dns_packet.provider.as_str(),
+                        if dns_packet.bypass {
+                            " (bypassing system resolvers)"
+                        } else {
+                            ""
+                        }
+                    );
+                } else {
+                    // Link-local queries have no resolver, so show who asked
+                    println!(
+                        "[{}] {}{} Query: {} (Type: {}{}) from {}{}",
+                        time,
+                        tag,
+                        dns_packet.protocol,
+                        query.name,
+                        query.query_type,
+                        flags,
+                        dns_packet.source,
+                        process
+                    );
+                }
+            }
+            if let Some(subnet) = dns_packet.edns.as_ref().and_then(|e| e.client_subnet()) {
+                println!("  Client Subnet: {}", subnet);
+            }
+        } else {
+            // mDNS responses usually carry no question, so name them by their first answer
+            let name = dns_packet
+                .question()
+                .map(|q| q.name.as_str())
+                .or_else(|| dns_packet.answers.first().map(|a| a.name.as_str()))
+                .unwrap_or("<no question>");
+            println!(
+                "[{}] {}{} Response: {} {} - {} answers",
+                time,
+                tag,
+                dns_packet.protocol,
+                name,
+                dns_packet.rcode_name(),
+                dns_packet.answers.len()
+            );
+
+            for (i, answer) in dns_packet.answers.iter().enumerate() {
+                println!("  {}. {}", i + 1, answer);
+            }
+            if let Some(edns) = &dns_packet.edns {
+                for error in edns.extended_errors() {
+                    println!("  {}", error);
+                }
+            }
+        }
+
+        if args.verbose {
+            println!(
+                "  ID: {:#06x}, Opcode: {:?}, Flags: [{}]",
+                header.id,
+                header.opcode,
+                header.flags()
+            );
+            println!(
+                "  From: {}",
+                SocketAddr::new(dns_packet.source, dns_packet.source_port)
+            );
             println!(
-                "DNS Query: {} (Type: {:?}) -> Estimated Provider: {}",
+                "  To: {}",
+                SocketAddr::new(dns_packet.destination, dns_packet.destination_port)
+            );
+            if dns_packet.checksum == ChecksumStatus::Invalid {
+                println!("  Warning: bad UDP checksum (possibly offloaded to the NIC)");
+            }
+            println!("  Transport: {}", dns_packet.transport.as_str());
+            println!("  Link: {}", dns_packet.link);
+            if let Some(interface) = &dns_packet.interface {
+                println!("  Interface: {}", interface);
+            }
+            if let Some(edns) = &dns_packet.edns {
+                println!("  EDNS: {}", edns);
+            }
+
+            if !dns_packet.authorities.is_empty() {
+                println!("  Authority:");
+                for record in &dns_packet.authorities {
+                    println!("    {}", record);
+                }
+            }
+            if !dns_packet.additionals.is_empty() {
+                println!("  Additional:");
+                for record in &dns_packet.additionals {
+                    println!("    {}", record);
+                }
+            }
+        }
+    }
+
+    fn handle_transaction(&self, transaction: DnsTransaction, args: &crate::cli::Args) {
+        let time =
+            DateTime::<Local>::from(transaction.response.timestamp).format("%Y-%m-%d %H:%M:%S%.3f");
+
+        if let Some(query) = transaction.query.question() {
+            println!(
+                "[{}] {}{} Answered: {} (Type: {}) {} by {} in {:.3} ms",
+                time,
+                interface_tag(transaction.query.interface.as_deref(), args),
+                transaction.query.protocol,
                 query.name,
                 query.query_type,
-                dns_packet.provider.as_str()
+                transaction.response.rcode_name(),
+                responder(&transaction.response),
+                transaction.rtt.as_secs_f64() * 1000.0
             );
+        }
+    }
 
-            if args.verbose {
-                println!("  From: {}", dns_packet.source);
-                println!("  To: {}", dns_packet.destination);
-            }
+    fn handle_timeout(&self, query: DnsPacket, args: &crate::cli::Args) {
+        let time = DateTime::<Local>::from(query.timestamp).format("%Y-%m-%d %H:%M:%S%.3f");
+
+        if let Some(question) = query.question() {
+            println!(
+                "[{}] {}{} Timeout: {} (Type: {}) to {} got no response within {:?}",
+                time,
+                interface_tag(query.interface.as_deref(), args),
+                query.protocol,
+                question.name,
+                question.query_type,
+                responder(&query),
+                args.query_timeout
+            );
         }
+    }
 
-        if !dns_packet.answers.is_empty() {
-            println!("DNS Response: {} answers", dns_packet.answers.len());
+    fn handle_encrypted_dns(&self, update: EncryptedDnsUpdate, args: &crate::cli::Args) {
+        let session = &update.session;
+        let server_name = session
+            .server_name
+            .as_ref()
+            .map(|name| format!(" ({})", name))
+            .unwrap_or_default();
+        let tag = interface_tag(session.interface.as_deref(), args);
 
-            for (i, answer) in dns_packet.answers.iter().enumerate() {
-                println!("  {}. {} -> {}", i + 1, answer.name, answer.data);
+        match update.event {
+            SessionEvent::Opened => {
+                let time = DateTime::<Local>::from(session.started).format("%Y-%m-%d %H:%M:%S%.3f");
+                println!(
+                    "[{}] {}Encrypted DNS: {} session to {}{} opened",
+                    time,
+                    tag,
+                    session.protocol.as_str(),
+                    session.provider.as_str(),
+                    server_name
+                );
+                if args.verbose {
+                    println!("  From: {}", session.client);
+                    println!("  To: {}", session.server);
+                }
+            }
+            SessionEvent::Closed => {
+                let time =
+                    DateTime::<Local>::from(session.last_seen).format("%Y-%m-%d %H:%M:%S%.3f");
+                let duration = session
+                    .last_seen
+                    .duration_since(session.started)
+                    .unwrap_or_default();
+                println!(
+                    "[{}] {}Encrypted DNS: {} session to {}{} closed after {:.3} s - {} packets, {} bytes",
+                    time,
+                    tag,
+                    session.protocol.as_str(),
+                    session.provider.as_str(),
+                    server_name,
+                    duration.as_secs_f64(),
+                    session.packets,
+                    session.bytes
+                );
             }
+            SessionEvent::Traffic { .. } => {}
         }
     }
 
@@ -0,0 +1,320 @@
+use std::{borrow::Cow, cell::Cell, net::IpAddr};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+use crate::{
+    attribution::ProcessInfo,
+    dns::{
+        DnsPacket, DnsRecord, DnsTransaction, Edns, EncryptedDnsSession, EncryptedDnsUpdate,
+        SessionEvent,
+    },
+    net::ChecksumStatus,
+};
+
+use super::PacketHandler;
+
+/// Version of the record layout described in the README. Adding fields keeps
+/// the version; renaming, removing or changing the meaning of a field bumps it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum RecordType {
+    Query,
+    Response,
+    /// A response matched to its query, carrying the round trip time
+    Answered,
+    Timeout,
+    /// A finished DoT, DoH or DoQ session, see SessionRecord
+    EncryptedSession,
+}
+
+#[derive(Serialize)]
+struct Answer {
+    name: String,
+    ttl: u32,
+    #[serde(rename = "type")]
+    record_type: Cow<'static, str>,
+    data: String,
+    cache_flush: bool,
+}
+
+impl From<&DnsRecord> for Answer {
+    fn from(record: &DnsRecord) -> Self {
+        Self {
+            name: record.name.clone(),
+            ttl: record.ttl,
+            record_type: record.data.type_name(),
+            data: record.data.to_string(),
+            cache_flush: record.cache_flush,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ExtendedError {
+    code: u16,
+    name: &'static str,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct EdnsInfo {
+    udp_payload_size: u16,
+    dnssec_ok: bool,
+    client_subnet: Option<String>,
+    extended_errors: Vec<ExtendedError>,
+}
+
+impl From<&Edns> for EdnsInfo {
+    fn from(edns: &Edns) -> Self {
+        Self {
+            udp_payload_size: edns.udp_payload_size,
+            dnssec_ok: edns.dnssec_ok,
+            client_subnet: edns
+                .client_subnet()
+                .map(|subnet| format!("{}/{}", subnet.address, subnet.source_prefix)),
+            extended_errors: edns
+                .extended_errors()
+                .map(|error| ExtendedError {
+                    code: error.code,
+                    name: error.code_name(),
+                    text: error.text.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ProcessRecord {
+    pid: u32,
+    name: String,
+}
+
+impl From<&ProcessInfo> for ProcessRecord {
+    fn from(process: &ProcessInfo) -> Self {
+        Self {
+            pid: process.pid,
+            name: process.name.clone(),
+        }
+    }
+}
+
+/// One output line (or object, when pretty-printed). Every record type has the
+/// same fields so consumers can use a single schema; fields that don't apply
+/// to a type are null or empty.
+#[derive(Serialize)]
+struct Record {
+    schema_version: u32,
+    #[serde(rename = "type")]
+    record_type: RecordType,
+    /// RFC 3339 capture time in UTC
+    timestamp: String,
+    id: u16,
+    protocol: &'static str,
+    transport: &'static str,
+    source_ip: IpAddr,
+    source_port: u16,
+    destination_ip: IpAddr,
+    destination_port: u16,
+    interface: Option<String>,
+    /// Local process that sent the query
+    process: Option<ProcessRecord>,
+    provider: &'static str,
+    bypass: bool,
+    qname: Option<String>,
+    qtype: Option<String>,
+    unicast_response: bool,
+    rcode: Option<String>,
+    flags: Vec<String>,
+    answers: Vec<Answer>,
+    rtt_ms: Option<f64>,
+    edns: Option<EdnsInfo>,
+    bad_checksum: bool,
+}
+
+impl Record {
+    fn new(record_type: RecordType, packet: &DnsPacket) -> Self {
+        let question = packet.question();
+        let is_response = packet.header.is_response;
+
+        Self {
+            schema_version: SCHEMA_VERSION,
+            record_type,
+            timestamp: DateTime::<Utc>::from(packet.timestamp)
+                .to_rfc3339_opts(SecondsFormat::Micros, true),
+            id: packet.header.id,
+            protocol: packet.protocol.as_str(),
+            transport: packet.transport.as_str(),
+            source_ip: packet.source,
+            source_port: packet.source_port,
+            destination_ip: packet.destination,
+            destination_port: packet.destination_port,
+            interface: packet.interface.clone(),
+            process: packet.process.as_ref().map(ProcessRecord::from),
+            provider: packet.provider.as_str(),
+            bypass: packet.bypass,
+            qname: question.map(|q| q.name.clone()),
+            qtype: question.map(|q| q.query_type.to_string()),
+            unicast_response: question.is_some_and(|q| q.unicast_response),
+            rcode: is_response.then(|| packet.rcode_name()),
+            flags: packet
+                .header
+                .flags()
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+            answers: packet.answers.iter().map(Answer::from).collect(),
+            rtt_ms: None,
+            edns: packet.edns.as_ref().map(EdnsInfo::from),
+            bad_checksum: packet.checksum == ChecksumStatus::Invalid,
+        }
+    }
+}
+
+/// An encrypted DNS session, emitted once it closes. The messages inside
+/// can't be seen, so this has its own shape rather than the DNS record's.
+#[derive(Serialize)]
+struct SessionRecord {
+    schema_version: u32,
+    #[serde(rename = "type")]
+    record_type: RecordType,
+    /// RFC 3339 time the session was first seen, in UTC
+    timestamp: String,
+    protocol: &'static str,
+    source_ip: IpAddr,
+    source_port: u16,
+    destination_ip: IpAddr,
+    destination_port: u16,
+    interface: Option<String>,
+    provider: &'static str,
+    server_name: Option<String>,
+    packets: u64,
+    bytes: u64,
+    duration_ms: f64,
+}
+
+impl From<&EncryptedDnsSession> for SessionRecord {
+    fn from(session: &EncryptedDnsSession) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            record_type: RecordType::EncryptedSession,
+            timestamp: DateTime::<Utc>::from(session.started)
+                .to_rfc3339_opts(SecondsFormat::Micros, true),
+            protocol: session.protocol.as_str(),
+            source_ip: session.client.ip(),
+            source_port: session.client.port(),
+            destination_ip: session.server.ip(),
+            destination_port: session.server.port(),
+            interface: session.interface.clone(),
+            provider: session.provider.as_str(),
+            server_name: session.server_name.clone(),
+            packets: session.packets,
+            bytes: session.bytes,
+            duration_ms: session
+                .last_seen
+                .duration_since(session.started)
+                .unwrap_or_default()
+                .as_secs_f64()
+                * 1000.0,
+        }
+    }
+}
+
+/// Writes machine-readable records to stdout, either one compact object per
+/// line (NDJSON) or as a single pretty-printed array
+pub struct JsonOutput {
+    array: bool,
+    /// Whether a record has been written yet, so the next one is preceded by
+    /// a comma rather than opening the array
+    written: Cell<bool>,
+}
+
+impl JsonOutput {
+    /// A pretty-printed JSON array, closed by `finish`
+    pub fn array() -> Self {
+        Self {
+            array: true,
+            written: Cell::new(false),
+        }
+    }
+
+    /// One compact JSON object per line
+    pub fn lines() -> Self {
+        Self {
+            array: false,
+            written: Cell::new(false),
+        }
+    }
+
+    fn emit(&self, record: &impl Serialize) {
+        let serialized = if self.array {
+            serde_json::to_string_pretty(record)
+        } else {
+            serde_json::to_string(record)
+        };
+        let record = match serialized {
+            Ok(record) => record,
+            Err(e) => {
+                eprintln!("Failed to serialize record: {}", e);
+                return;
+            }
+        };
+        if !self.array {
+            println!("{}", record);
+        } else if self.written.replace(true) {
+            print!(",\n{}", record);
+        } else {
+            print!("[\n{}", record);
+        }
+    }
+}
+
+impl PacketHandler for JsonOutput {
+    fn handle_dns_packet(&self, dns_packet: DnsPacket, _args: &crate::cli::Args) {
+        let record_type = if dns_packet.header.is_response {
+            RecordType::Response
+        } else {
+            RecordType::Query
+        };
+        self.emit(&Record::new(record_type, &dns_packet));
+    }
+
+    // A matched response is written once, as an `answered` record
+    fn wants_matched_responses(&self) -> bool {
+        false
+    }
+
+    fn handle_transaction(&self, transaction: DnsTransaction, _args: &crate::cli::Args) {
+        let mut record = Record::new(RecordType::Answered, &transaction.response);
+        record.rtt_ms = Some(transaction.rtt.as_secs_f64() * 1000.0);
+        // Only the query can be traced to the process that sent it
+        record.process = transaction.query.process.as_ref().map(ProcessRecord::from);
+        self.emit(&record);
+    }
+
+    fn handle_timeout(&self, query: DnsPacket, _args: &crate::cli::Args) {
+        self.emit(&Record::new(RecordType::Timeout, &query));
+    }
+
+    fn handle_encrypted_dns(&self, update: EncryptedDnsUpdate, _args: &crate::cli::Args) {
+        if update.event == SessionEvent::Closed {
+            self.emit(&SessionRecord::from(&update.session));
+        }
+    }
+
+    fn finish(&self) {
+        if !self.array {
+            return;
+        }
+        if self.written.get() {
+            println!("\n]");
+        } else {
+            println!("[]");
+        }
+    }
+
+    fn handle_network_packet(&self, _packet: &pcap::Packet, _args: &crate::cli::Args) {}
+}
@@ -1,17 +1,35 @@
 use crate::cli::Args;
-use crate::{dns::DnsPacket, shared::TxEvent};
+use crate::{
+    dns::{DnsPacket, DnsTransaction, EncryptedDnsUpdate},
+    shared::TxEvent,
+};
 use std::sync::mpsc::Sender;
 
 pub mod cli_output;
+pub mod json_output;
 pub mod tui_output;
 
 pub use cli_output::*;
+pub use json_output::*;
 pub use tui_output::*;
 
 pub type Tx = Sender<TxEvent>;
 
 pub trait PacketHandler {
     fn handle_dns_packet(&self, d: DnsPacket, args: &Args);
+    /// Whether responses matched to a query are passed to handle_dns_packet
+    /// as well as handle_transaction, rather than to handle_transaction alone
+    fn wants_matched_responses(&self) -> bool {
+        true
+    }
+    /// Called when a response has been matched to its query
+    fn handle_transaction(&self, t: DnsTransaction, args: &Args);
+    /// Called when a query has gone unanswered for longer than the query timeout
+    fn handle_timeout(&self, query: DnsPacket, args: &Args);
+    /// Called when an encrypted DNS session opens, carries traffic or closes
+    fn handle_encrypted_dns(&self, update: EncryptedDnsUpdate, args: &Args);
+    /// Called once capturing has stopped, to finish off the output
+    fn finish(&self) {}
     // TODO: do more with other packets ?
     #[allow(unused)]
     fn handle_network_packet(&self, d: &pcap::Packet, args: &Args);
@@ -1,30 +1,122 @@
-use std::time::SystemTime;
-
-use crate::shared::TxEvent;
+use crate::{
+    dns::{DnsPacket, DnsTransaction, EncryptedDnsUpdate},
+    shared::TxEvent,
+};
 
 use super::{PacketHandler, Tx};
 
 pub struct ChannelOutput(pub Tx);
 
+impl ChannelOutput {
+    // Name the response after its question or, for announcements, which have
+    // none, its first answer
+    fn send_response(&self, response: DnsPacket) {
+        let (domain, query_type) = match (response.question(), response.answers.first()) {
+            (Some(question), _) => (question.name.clone(), question.query_type.to_string()),
+            (None, Some(answer)) => (answer.name.clone(), answer.data.type_name().into_owned()),
+            (None, None) => return,
+        };
+        self.0
+            .send(TxEvent::DnsResponse {
+                domain,
+                query_type,
+                protocol: response.protocol,
+                source: response.source.to_string(),
+                destination: response.destination.to_string(),
+                interface: response.interface.clone(),
+                timestamp: response.timestamp,
+                response: Box::new(response),
+            })
+            .ok();
+    }
+}
+
 impl PacketHandler for ChannelOutput {
     fn handle_network_packet(&self, _packet: &pcap::Packet, _args: &crate::cli::Args) {
         // TODO: do something with regular network packets!
-        ()
     }
 
-    fn handle_dns_packet(&self, dns_packet: crate::dns::DnsPacket, _args: &crate::cli::Args) {
-        if let Some(query) = dns_packet.query {
+    // Matched responses are reported with their query, see handle_transaction
+    fn wants_matched_responses(&self) -> bool {
+        false
+    }
+
+    fn handle_dns_packet(&self, dns_packet: DnsPacket, _args: &crate::cli::Args) {
+        if dns_packet.header.is_response {
+            // A unicast response without its query is only missing its
+            // transaction, but link-local responders announce their records
+            // unprompted, and those are worth showing on their own
+            if !dns_packet.protocol.is_unicast() {
+                self.send_response(dns_packet);
+            }
+            return;
+        }
+
+        for query in &dns_packet.questions {
             self.0
                 .send(TxEvent::DnsQuery {
                     domain: query.name.clone(),
-                    query_type: format!("{:?}", query.query_type),
+                    query_type: query.query_type.to_string(),
+                    protocol: dns_packet.protocol,
                     provider: dns_packet.provider,
-                    source: dns_packet.source.clone(),
-                    destination: dns_packet.destination.clone(),
-                    timestamp: SystemTime::now(),
+                    source: dns_packet.source.to_string(),
+                    destination: dns_packet.destination.to_string(),
+                    bypass: dns_packet.bypass,
+                    interface: dns_packet.interface.clone(),
+                    process: dns_packet.process.clone(),
+                    edns: dns_packet.edns.clone(),
+                    timestamp: dns_packet.timestamp,
+                    packet: Box::new(dns_packet.clone()),
+                })
+                .ok();
+        }
+    }
+
+    fn handle_transaction(&self, transaction: DnsTransaction, _args: &crate::cli::Args) {
+        let query = transaction.query;
+        let response = transaction.response;
+        if let Some(question) = query.question() {
+            self.0
+                .send(TxEvent::DnsTransaction {
+                    domain: question.name.clone(),
+                    query_type: question.query_type.to_string(),
+                    protocol: query.protocol,
+                    provider: response.provider,
+                    source: query.source.to_string(),
+                    destination: query.destination.to_string(),
+                    rcode: response.rcode_name(),
+                    rtt: transaction.rtt,
+                    edns: response.edns.clone(),
+                    timestamp: query.timestamp,
+                    response: Box::new(response),
                 })
                 .ok();
         }
-        // TODO: handle answers
+    }
+
+    fn handle_timeout(&self, query: DnsPacket, _args: &crate::cli::Args) {
+        if let Some(question) = query.question() {
+            self.0
+                .send(TxEvent::DnsTimeout {
+                    domain: question.name.clone(),
+                    query_type: question.query_type.to_string(),
+                    protocol: query.protocol,
+                    provider: query.provider,
+                    source: query.source.to_string(),
+                    destination: query.destination.to_string(),
+                    timestamp: query.timestamp,
+                })
+                .ok();
+        }
+    }
+
+    fn handle_encrypted_dns(&self, update: EncryptedDnsUpdate, _args: &crate::cli::Args) {
+        self.0
+            .send(TxEvent::EncryptedDnsSession {
+                provider: update.session.provider,
+                protocol: update.session.protocol.as_str().to_string(),
+                event: update.event,
+            })
+            .ok();
     }
 }
@@ -0,0 +1,75 @@
+use std::fmt;
+
+/// Counters kept by pcap for one capture
+#[derive(Debug, Clone)]
+pub struct SourceStats {
+    /// Interface the capture is on
+    pub name: String,
+    /// Packets that passed the capture filter
+    pub received: u32,
+    /// Packets the kernel dropped because they weren't read fast enough
+    pub dropped: u32,
+    /// Packets dropped by the interface or its driver
+    pub if_dropped: u32,
+}
+
+/// What a capture saw, printed when dustcloud exits
+#[derive(Debug, Clone, Default)]
+pub struct CaptureSummary {
+    /// Packets read from the captures
+    pub packets: u64,
+    /// Copies of packets already seen on another interface
+    pub duplicates: u64,
+    /// Packets carrying DNS, whether messages or encrypted sessions
+    pub parsed: u64,
+    /// DNS messages decoded from those packets
+    pub messages: u64,
+    /// Queries still waiting for a response when capturing was stopped
+    pub pending: usize,
+    /// pcap's own counters; savefiles have none
+    pub sources: Vec<SourceStats>,
+}
+
+impl fmt::Display for CaptureSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Capture summary:")?;
+        write!(f, "  Packets seen: {}", self.packets)?;
+        if self.duplicates > 0 {
+            write!(
+                f,
+                " ({} duplicates from other interfaces ignored)",
+                self.duplicates
+            )?;
+        }
+        writeln!(f)?;
+        write!(
+            f,
+            "  Packets parsed: {} ({} DNS messages)",
+            self.parsed, self.messages
+        )?;
+
+        if !self.sources.is_empty() {
+            let dropped: u64 = self.sources.iter().map(|s| s.dropped as u64).sum();
+            let if_dropped: u64 = self.sources.iter().map(|s| s.if_dropped as u64).sum();
+            write!(
+                f,
+                "\n  Dropped: {} by the kernel, {} by the interface",
+                dropped, if_dropped
+            )?;
+            // Break it down when there's more than one capture to blame
+            if self.sources.len() > 1 {
+                for source in &self.sources {
+                    write!(
+                        f,
+                        "\n    {}: {} received, {} dropped by the kernel, {} by the interface",
+                        source.name, source.received, source.dropped, source.if_dropped
+                    )?;
+                }
+            }
+        }
+        if self.pending > 0 {
+            write!(f, "\n  Queries still awaiting a response: {}", self.pending)?;
+        }
+        Ok(())
+    }
+}
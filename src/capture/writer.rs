@@ -0,0 +1,217 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use pcap::{Capture, Dead, Linktype, Packet, Savefile};
+
+use crate::{cli::Args, dns::packet_timestamp};
+
+/// Size of the pcap global header and of each per-packet record header
+const FILE_HEADER_LEN: u64 = 24;
+const RECORD_HEADER_LEN: u64 = 16;
+/// How often buffered packets are flushed so the file can be read while we run
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// When to start a new savefile, and how many to keep
+#[derive(Debug, Clone)]
+pub struct Rotation {
+    pub max_size: Option<u64>,
+    pub interval: Option<Duration>,
+    pub keep: Option<usize>,
+}
+
+impl Rotation {
+    fn enabled(&self) -> bool {
+        self.max_size.is_some() || self.interval.is_some()
+    }
+}
+
+struct OpenFile {
+    savefile: Savefile,
+    bytes: u64,
+    /// Capture time of the first packet written, used for interval rotation
+    started: SystemTime,
+}
+
+/// Writes every captured packet to a pcap savefile. With rotation enabled the
+/// files are named `<stem>-<time>-<n>.<ext>` next to the requested path and the
+/// oldest are deleted once there are more than `keep`.
+pub struct PcapWriter {
+    dead: Capture<Dead>,
+    path: PathBuf,
+    rotation: Rotation,
+    current: Option<OpenFile>,
+    /// Files written by this run, oldest first
+    written: VecDeque<PathBuf>,
+    sequence: u32,
+    last_flush: Instant,
+}
+
+impl PcapWriter {
+    pub fn new(path: &Path, linktype: Linktype, rotation: Rotation) -> Result<Self> {
+        let dead = Capture::dead(linktype).context("Failed to set up pcap writer")?;
+        Ok(Self {
+            dead,
+            path: path.to_path_buf(),
+            rotation,
+            current: None,
+            written: VecDeque::new(),
+            sequence: 0,
+            last_flush: Instant::now(),
+        })
+    }
+
+    /// Build the writer requested on the command line, if any. When capturing
+    /// on several interfaces each gets its own files, named after `interface`.
+    pub fn from_args(
+        args: &Args,
+        linktype: Linktype,
+        interface: Option<&str>,
+    ) -> Result<Option<Self>> {
+        let Some(path) = &args.write else {
+            return Ok(None);
+        };
+        let path = match interface {
+            Some(interface) => interface_path(path, interface),
+            None => path.clone(),
+        };
+        let rotation = Rotation {
+            max_size: args.write_max_size,
+            interval: args.write_interval,
+            keep: args.write_keep,
+        };
+        Self::new(&path, linktype, rotation).map(Some)
+    }
+
+    pub fn write(&mut self, packet: &Packet) -> Result<()> {
+        let timestamp = packet_timestamp(packet.header);
+        let record_len = RECORD_HEADER_LEN + packet.header.caplen as u64;
+
+        if self.should_rotate(timestamp, record_len) {
+            self.rotate(timestamp)?;
+        }
+
+        let file = self.current.as_mut().expect("savefile opened by rotate");
+        file.savefile.write(packet);
+        file.bytes += record_len;
+
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        if let Some(file) = &mut self.current {
+            file.savefile
+                .flush()
+                .context("Failed to flush pcap savefile")?;
+        }
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+
+    fn should_rotate(&self, timestamp: SystemTime, record_len: u64) -> bool {
+        let Some(file) = &self.current else {
+            return true;
+        };
+
+        // Always write at least one packet per file, however small the limit
+        let over_size = self
+            .rotation
+            .max_size
+            .is_some_and(|max| file.bytes > FILE_HEADER_LEN && file.bytes + record_len > max);
+        let over_time = self.rotation.interval.is_some_and(|interval| {
+            timestamp.duration_since(file.started).unwrap_or_default() >= interval
+        });
+        over_size || over_time
+    }
+
+    fn rotate(&mut self, timestamp: SystemTime) -> Result<()> {
+        if let Some(mut file) = self.current.take() {
+            file.savefile
+                .flush()
+                .context("Failed to flush pcap savefile")?;
+        }
+
+        let path = self.next_path(timestamp);
+        let savefile = self
+            .dead
+            .savefile(&path)
+            .with_context(|| format!("Failed to create pcap savefile '{}'", path.display()))?;
+        self.current = Some(OpenFile {
+            savefile,
+            bytes: FILE_HEADER_LEN,
+            started: timestamp,
+        });
+        self.written.push_back(path);
+        self.prune()
+    }
+
+    fn next_path(&mut self, timestamp: SystemTime) -> PathBuf {
+        if !self.rotation.enabled() {
+            return self.path.clone();
+        }
+
+        self.sequence += 1;
+        let stem = self
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "dustcloud".to_string());
+        let extension = self
+            .path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_else(|| "pcap".to_string());
+        let time = DateTime::<Local>::from(timestamp).format("%Y%m%d-%H%M%S");
+
+        self.path
+            .with_file_name(format!("{}-{}-{}.{}", stem, time, self.sequence, extension))
+    }
+
+    /// Delete the oldest files written by this run beyond the retention count
+    fn prune(&mut self) -> Result<()> {
+        let Some(keep) = self.rotation.keep else {
+            return Ok(());
+        };
+
+        while self.written.len() > keep {
+            if let Some(old) = self.written.pop_front() {
+                fs::remove_file(&old).with_context(|| {
+                    format!("Failed to remove old pcap savefile '{}'", old.display())
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `dns.pcap` becomes `dns-en0.pcap`. Characters that don't belong in a file
+/// name (e.g. in Windows device names) are replaced.
+fn interface_path(path: &Path, interface: &str) -> PathBuf {
+    let interface: String = interface
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "dustcloud".to_string());
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, interface, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, interface),
+    };
+    path.with_file_name(file_name)
+}
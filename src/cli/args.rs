@@ -1,6 +1,25 @@
-use clap::Parser;
+use std::{path::PathBuf, time::Duration};
+
+use clap::{Parser, ValueEnum};
 
 use crate::capture::dns_providers::{list_all_providers, DnsProvider};
+use crate::display_filter::DisplayFilter;
+use crate::dns::DnsProtocol;
+use crate::util::{parse_duration, parse_size};
+
+/// Passed to `--device` to capture on every interface that is up
+pub const ALL_DEVICES: &str = "all";
+
+/// How records are written when the TUI is disabled
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable log lines
+    Text,
+    /// A single pretty-printed JSON array of records
+    Json,
+    /// One compact JSON object per line (newline-delimited JSON)
+    Ndjson,
+}
 
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about = "A tool for monitoring DNS requests on macOS")]
@@ -12,13 +31,57 @@ pub struct Args {
     #[arg(long)]
     pub disable_tui: bool,
 
-    /// Specify DNS providers to monitor (comma-separated: cloudflare,google,opendns,quad9,adguard,cleanbrowsing)
+    /// Output format when the TUI is disabled
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output_format: OutputFormat,
+
+    /// Specify DNS providers to monitor by catalog name (comma-separated, e.g. cloudflare,google)
     #[arg(long, value_delimiter = ',')]
     pub dns_providers: Option<Vec<String>>,
 
-    /// Specify network interface to use (e.g., en0)
-    #[arg(short = 'i', long)]
-    pub device: Option<String>,
+    /// Name resolution protocols to capture (comma-separated) [default: all, or dns with --dns-providers]
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub protocols: Option<Vec<DnsProtocol>>,
+
+    /// Extra BPF expression that captured packets must also match (e.g. 'host 10.0.0.5')
+    #[arg(long, value_name = "EXPR")]
+    pub bpf: Option<String>,
+
+    /// Only show DNS messages matching this expression (e.g. 'qname ~ "*.example.com" && rcode != NOERROR')
+    #[arg(long, value_name = "EXPR")]
+    pub filter: Option<DisplayFilter>,
+
+    /// Provider catalog to load instead of ~/.config/dustcloud/providers.toml
+    #[arg(long, value_name = "FILE")]
+    pub providers_file: Option<PathBuf>,
+
+    /// Network interface to capture on (e.g., en0). Repeat for several, or use "all"
+    #[arg(short = 'i', long = "device", value_name = "DEVICE")]
+    pub devices: Vec<String>,
+
+    /// Read packets from a saved pcap/pcapng file instead of capturing live
+    #[arg(short = 'r', long, value_name = "FILE")]
+    pub read: Option<PathBuf>,
+
+    /// Also write every captured packet to a pcap savefile
+    #[arg(short = 'w', long, value_name = "FILE")]
+    pub write: Option<PathBuf>,
+
+    /// Start a new savefile once the current one reaches this size (e.g. 100M)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub write_max_size: Option<u64>,
+
+    /// Start a new savefile after this much capture time (e.g. 1h)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub write_interval: Option<Duration>,
+
+    /// Keep only the newest N rotated savefiles, deleting older ones
+    #[arg(long, value_name = "N")]
+    pub write_keep: Option<usize>,
+
+    /// User to switch to once capturing has started as root [default: the user running sudo, or nobody]
+    #[arg(long, value_name = "USER")]
+    pub user: Option<String>,
 
     /// Enable verbose output
     #[arg(short, long)]
@@ -28,6 +91,14 @@ pub struct Args {
     #[arg(short = 'l', long)]
     pub list_devices: bool,
 
+    /// How long to wait for a response before flagging a query as timed out (e.g. 5s, 500ms)
+    #[arg(long, value_name = "DURATION", default_value = "5s", value_parser = parse_duration)]
+    pub query_timeout: Duration,
+
+    /// Time span of the TUI's traffic chart (e.g. 60s, 5m)
+    #[arg(long, value_name = "DURATION", default_value = "60s", value_parser = parse_duration)]
+    pub window: Duration,
+
     /// Continue capturing on error
     #[arg(long)]
     pub continue_on_error: bool,
@@ -35,9 +106,12 @@ pub struct Args {
 
 impl Args {
     pub fn validate(&self) -> Result<(), String> {
-        if let Err(v) = self.validate_dns_providers() {
-            return Err(v);
-        }
+        self.validate_dns_providers()?;
+        self.validate_devices()?;
+        self.validate_read()?;
+        self.validate_output_format()?;
+        self.validate_write()?;
+        self.validate_window()?;
 
         Ok(())
     }
@@ -45,7 +119,7 @@ impl Args {
     fn validate_dns_providers(&self) -> Result<(), String> {
         if let Some(providers) = &self.dns_providers {
             for provider in providers {
-                if DnsProvider::from_str(provider) == DnsProvider::Unknown {
+                if DnsProvider::from_str(provider) == DnsProvider::UNKNOWN {
                     return Err(format!(
                         "Unknown DNS provider: {}. Available providers: {}",
                         provider,
@@ -54,13 +128,101 @@ impl Args {
                 }
             }
         }
+        if self.dns_providers.is_some() && !self.get_protocols().contains(&DnsProtocol::Dns) {
+            return Err("--dns-providers only applies to dns; add it to --protocols".to_string());
+        }
         if self.disable_tui && self.verbose {
-            return Err(format!("Can't supply verbose argument with tui enabled"));
+            return Err("Can't supply verbose argument with tui enabled".to_string());
+        }
+
+        Ok(())
+    }
+
+    fn validate_devices(&self) -> Result<(), String> {
+        if self.captures_all_devices() && self.devices.len() > 1 {
+            return Err(format!(
+                "Can't name other devices alongside --device {}",
+                ALL_DEVICES
+            ));
+        }
+        for (i, device) in self.devices.iter().enumerate() {
+            if self.devices[..i].contains(device) {
+                return Err(format!("Device '{}' is given more than once", device));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_read(&self) -> Result<(), String> {
+        if let Some(path) = &self.read {
+            if !self.devices.is_empty() {
+                return Err("Can't supply a network device when reading from a file".to_string());
+            }
+            if !path.is_file() {
+                return Err(format!("Capture file not found: {}", path.display()));
+            }
         }
 
         Ok(())
     }
 
+    fn validate_output_format(&self) -> Result<(), String> {
+        if self.output_format != OutputFormat::Text && !self.disable_tui {
+            return Err("--output-format json/ndjson requires --disable-tui".to_string());
+        }
+
+        Ok(())
+    }
+
+    fn validate_write(&self) -> Result<(), String> {
+        let rotating = self.write_max_size.is_some() || self.write_interval.is_some();
+        if self.write.is_none() && (rotating || self.write_keep.is_some()) {
+            return Err("Savefile rotation options require --write".to_string());
+        }
+        if self.write_keep.is_some() && !rotating {
+            return Err("--write-keep requires --write-max-size or --write-interval".to_string());
+        }
+        if self.write_keep == Some(0) {
+            return Err("--write-keep must be at least 1".to_string());
+        }
+        if let (Some(write), Some(read)) = (&self.write, &self.read)
+            && write == read
+        {
+            return Err("Can't write to the file being read".to_string());
+        }
+
+        Ok(())
+    }
+
+    fn validate_window(&self) -> Result<(), String> {
+        if self.window < Duration::from_secs(10) {
+            return Err("--window must be at least 10s".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// The protocols to capture. Providers only apply to plain DNS, so picking
+    /// some narrows the default to it.
+    pub fn get_protocols(&self) -> Vec<DnsProtocol> {
+        match (&self.protocols, &self.dns_providers) {
+            (Some(protocols), _) => protocols.clone(),
+            (None, Some(_)) => vec![DnsProtocol::Dns],
+            (None, None) => DnsProtocol::ALL.to_vec(),
+        }
+    }
+
+    /// Whether `--device all` was given
+    pub fn captures_all_devices(&self) -> bool {
+        self.devices.iter().any(|device| device == ALL_DEVICES)
+    }
+
+    /// Whether packets may come from more than one interface
+    pub fn captures_multiple_devices(&self) -> bool {
+        self.devices.len() > 1 || self.captures_all_devices()
+    }
+
     pub fn get_dns_providers(&self) -> Vec<DnsProvider> {
         if let Some(providers) = &self.dns_providers {
             providers.iter().map(|p| DnsProvider::from_str(p)).collect()
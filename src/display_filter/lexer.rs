@@ -0,0 +1,155 @@
+use super::FilterError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    /// Glob match, `~`
+    Matches,
+    /// Negated glob match, `!~`
+    NotMatches,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+            CompareOp::Matches => "~",
+            CompareOp::NotMatches => "!~",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Compare(CompareOp),
+    /// A field name or unquoted value, e.g. `qtype` or `10.0.0.0/8`
+    Word(String),
+    /// A double-quoted value, with escapes resolved
+    Str(String),
+    End,
+}
+
+impl TokenKind {
+    /// How the token is named in error messages
+    pub fn describe(&self) -> String {
+        match self {
+            TokenKind::LParen => "`(`".to_string(),
+            TokenKind::RParen => "`)`".to_string(),
+            TokenKind::And => "`&&`".to_string(),
+            TokenKind::Or => "`||`".to_string(),
+            TokenKind::Not => "`!`".to_string(),
+            TokenKind::Compare(op) => format!("`{}`", op.as_str()),
+            TokenKind::Word(word) => format!("`{}`", word),
+            TokenKind::Str(value) => format!("\"{}\"", value),
+            TokenKind::End => "the end of the filter".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Byte offset of the token in the expression
+    pub position: usize,
+}
+
+/// Characters that end an unquoted word
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || "()!=<>~&|\"".contains(c)
+}
+
+/// Split a filter expression into tokens, ending with `TokenKind::End`
+pub fn tokenize(expression: &str) -> Result<Vec<Token>, FilterError> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.char_indices().peekable();
+
+    while let Some(&(position, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        chars.next();
+        let next = chars.peek().map(|&(_, c)| c);
+        let mut two_chars = |kind| {
+            chars.next();
+            kind
+        };
+
+        let kind = match (c, next) {
+            ('(', _) => TokenKind::LParen,
+            (')', _) => TokenKind::RParen,
+            ('&', Some('&')) => two_chars(TokenKind::And),
+            ('|', Some('|')) => two_chars(TokenKind::Or),
+            ('=', Some('=')) => two_chars(TokenKind::Compare(CompareOp::Eq)),
+            ('!', Some('=')) => two_chars(TokenKind::Compare(CompareOp::Ne)),
+            ('!', Some('~')) => two_chars(TokenKind::Compare(CompareOp::NotMatches)),
+            ('<', Some('=')) => two_chars(TokenKind::Compare(CompareOp::Le)),
+            ('>', Some('=')) => two_chars(TokenKind::Compare(CompareOp::Ge)),
+            ('!', _) => TokenKind::Not,
+            ('~', _) => TokenKind::Compare(CompareOp::Matches),
+            ('<', _) => TokenKind::Compare(CompareOp::Lt),
+            ('>', _) => TokenKind::Compare(CompareOp::Gt),
+            ('&', _) => return Err(FilterError::new(position, "expected `&&`")),
+            ('|', _) => return Err(FilterError::new(position, "expected `||`")),
+            ('=', _) => return Err(FilterError::new(position, "use `==` to compare")),
+            ('"', _) => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((escape, '\\')) => match chars.next() {
+                            Some((_, c @ ('"' | '\\'))) => value.push(c),
+                            _ => {
+                                return Err(FilterError::new(
+                                    escape,
+                                    "only `\\\"` and `\\\\` can be escaped",
+                                ))
+                            }
+                        },
+                        Some((_, c)) => value.push(c),
+                        None => return Err(FilterError::new(position, "unterminated string")),
+                    }
+                }
+                TokenKind::Str(value)
+            }
+            (c, _) => {
+                let mut word = c.to_string();
+                while let Some(&(_, c)) = chars.peek() {
+                    if is_delimiter(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                match word.as_str() {
+                    "and" => TokenKind::And,
+                    "or" => TokenKind::Or,
+                    "not" => TokenKind::Not,
+                    _ => TokenKind::Word(word),
+                }
+            }
+        };
+        tokens.push(Token { kind, position });
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::End,
+        position: expression.len(),
+    });
+    Ok(tokens)
+}
@@ -0,0 +1,362 @@
+use std::{error::Error, fmt, net::IpAddr, str::FromStr};
+
+use lexer::{tokenize, CompareOp};
+use parser::Parser;
+
+use crate::{capture::dns_providers::IpNetwork, dns::DnsPacket};
+
+mod lexer;
+mod parser;
+
+/// How a field's values are compared, which decides the operators it takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldKind {
+    /// Free text: `==`, `!=`, `~`, `!~`
+    Text,
+    /// Text from a fixed set of names, checked when compared with `==` or `!=`
+    Keyword,
+    /// Every comparison except `~` and `!~`
+    Number,
+    /// An address or CIDR network: `==`, `!=`
+    Address,
+    /// True or false on its own, e.g. `response` or `!bypass`
+    Flag,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Qname,
+    Qtype,
+    Rcode,
+    Protocol,
+    Transport,
+    Provider,
+    Interface,
+    Process,
+    Src,
+    Dst,
+    Host,
+    Sport,
+    Dport,
+    Port,
+    Id,
+    Pid,
+    Answers,
+    Response,
+    Bypass,
+    Truncated,
+}
+
+impl Field {
+    const ALL: [Field; 20] = [
+        Field::Qname,
+        Field::Qtype,
+        Field::Rcode,
+        Field::Protocol,
+        Field::Transport,
+        Field::Provider,
+        Field::Interface,
+        Field::Process,
+        Field::Src,
+        Field::Dst,
+        Field::Host,
+        Field::Sport,
+        Field::Dport,
+        Field::Port,
+        Field::Id,
+        Field::Pid,
+        Field::Answers,
+        Field::Response,
+        Field::Bypass,
+        Field::Truncated,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Field::Qname => "qname",
+            Field::Qtype => "qtype",
+            Field::Rcode => "rcode",
+            Field::Protocol => "protocol",
+            Field::Transport => "transport",
+            Field::Provider => "provider",
+            Field::Interface => "interface",
+            Field::Process => "process",
+            Field::Src => "src",
+            Field::Dst => "dst",
+            Field::Host => "host",
+            Field::Sport => "sport",
+            Field::Dport => "dport",
+            Field::Port => "port",
+            Field::Id => "id",
+            Field::Pid => "pid",
+            Field::Answers => "answers",
+            Field::Response => "response",
+            Field::Bypass => "bypass",
+            Field::Truncated => "truncated",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Field> {
+        Field::ALL.into_iter().find(|field| field.name() == name)
+    }
+
+    fn kind(&self) -> FieldKind {
+        match self {
+            Field::Qname | Field::Provider | Field::Interface | Field::Process => FieldKind::Text,
+            Field::Qtype | Field::Rcode | Field::Protocol | Field::Transport => FieldKind::Keyword,
+            Field::Src | Field::Dst | Field::Host => FieldKind::Address,
+            Field::Sport | Field::Dport | Field::Port | Field::Id | Field::Pid | Field::Answers => {
+                FieldKind::Number
+            }
+            Field::Response | Field::Bypass | Field::Truncated => FieldKind::Flag,
+        }
+    }
+
+    /// What the field's values are called in error messages
+    fn description(&self) -> &'static str {
+        match self {
+            Field::Qtype => "query type",
+            Field::Rcode => "response code",
+            _ => self.name(),
+        }
+    }
+
+    /// The field's text values in `packet`, lowercased. A message can have
+    /// several questions, and an interface and process only when captured live.
+    fn text(&self, packet: &DnsPacket) -> Vec<String> {
+        let values = match self {
+            Field::Qname => packet
+                .questions
+                .iter()
+                .map(|question| question.name.trim_end_matches('.').to_string())
+                .collect(),
+            Field::Qtype => packet
+                .questions
+                .iter()
+                .map(|question| question.query_type.to_string())
+                .collect(),
+            // Queries always carry NOERROR
+            Field::Rcode => vec![packet.rcode_name()],
+            Field::Protocol => vec![packet.protocol.as_str().to_string()],
+            Field::Transport => vec![packet.transport.as_str().to_string()],
+            Field::Provider => vec![packet.provider.as_str().to_string()],
+            Field::Interface => packet.interface.iter().cloned().collect(),
+            Field::Process => packet
+                .process
+                .iter()
+                .map(|process| process.name.clone())
+                .collect(),
+            _ => Vec::new(),
+        };
+        values
+            .into_iter()
+            .map(|value| value.to_lowercase())
+            .collect()
+    }
+
+    fn numbers(&self, packet: &DnsPacket) -> Vec<u64> {
+        match self {
+            Field::Sport => vec![packet.source_port as u64],
+            Field::Dport => vec![packet.destination_port as u64],
+            Field::Port => vec![packet.source_port as u64, packet.destination_port as u64],
+            Field::Id => vec![packet.header.id as u64],
+            Field::Pid => packet
+                .process
+                .iter()
+                .map(|process| process.pid as u64)
+                .collect(),
+            Field::Answers => vec![packet.answers.len() as u64],
+            _ => Vec::new(),
+        }
+    }
+
+    fn addresses(&self, packet: &DnsPacket) -> Vec<IpAddr> {
+        match self {
+            Field::Src => vec![packet.source],
+            Field::Dst => vec![packet.destination],
+            Field::Host => vec![packet.source, packet.destination],
+            _ => Vec::new(),
+        }
+    }
+
+    fn flag(&self, packet: &DnsPacket) -> bool {
+        match self {
+            Field::Response => packet.header.is_response,
+            Field::Bypass => packet.bypass,
+            Field::Truncated => packet.header.truncated,
+            _ => false,
+        }
+    }
+}
+
+/// A value checked against its field's kind when the filter was parsed
+#[derive(Debug, Clone)]
+enum Value {
+    /// Lowercased text, or a glob pattern for `~` and `!~`
+    Text(String),
+    Number(u64),
+    Network(IpNetwork),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Flag(Field),
+    Compare {
+        field: Field,
+        op: CompareOp,
+        value: Value,
+    },
+}
+
+impl Expr {
+    fn matches(&self, packet: &DnsPacket) -> bool {
+        match self {
+            Expr::And(left, right) => left.matches(packet) && right.matches(packet),
+            Expr::Or(left, right) => left.matches(packet) || right.matches(packet),
+            Expr::Not(expr) => !expr.matches(packet),
+            Expr::Flag(field) => field.flag(packet),
+            // `!=` and `!~` hold when the positive comparison holds for none of
+            // the field's values, e.g. `host != 1.1.1.1` for neither end
+            Expr::Compare { field, op, value } => match op {
+                CompareOp::Ne => !compare(*field, CompareOp::Eq, value, packet),
+                CompareOp::NotMatches => !compare(*field, CompareOp::Matches, value, packet),
+                op => compare(*field, *op, value, packet),
+            },
+        }
+    }
+}
+
+/// Whether any of the field's values in `packet` satisfies `op` (never `!=`
+/// or `!~`) against `value`
+fn compare(field: Field, op: CompareOp, value: &Value, packet: &DnsPacket) -> bool {
+    match value {
+        Value::Text(expected) => field.text(packet).iter().any(|text| match op {
+            CompareOp::Matches => glob_match(expected, text),
+            _ => text == expected,
+        }),
+        Value::Number(expected) => field.numbers(packet).iter().any(|number| match op {
+            CompareOp::Lt => number < expected,
+            CompareOp::Le => number <= expected,
+            CompareOp::Gt => number > expected,
+            CompareOp::Ge => number >= expected,
+            _ => number == expected,
+        }),
+        Value::Network(network) => field
+            .addresses(packet)
+            .into_iter()
+            .any(|address| network.contains(address)),
+    }
+}
+
+/// Match `text` against a pattern where `*` is any run of characters and
+/// `?` is any one character
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and of the text it's currently matched up to
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character and retry
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    t = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// A parsed `--filter` expression, evaluated against decoded DNS messages,
+/// e.g. `qname ~ "*.amazonaws.com" && qtype == AAAA && rcode != NOERROR`.
+/// Unlike the capture filter it can look inside messages, but every packet
+/// still has to be captured and decoded before it can be dropped.
+#[derive(Debug, Clone)]
+pub struct DisplayFilter {
+    expression: String,
+    expr: Expr,
+}
+
+impl DisplayFilter {
+    pub fn matches(&self, packet: &DnsPacket) -> bool {
+        self.expr.matches(packet)
+    }
+}
+
+impl FromStr for DisplayFilter {
+    type Err = FilterError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let expr = tokenize(expression)
+            .and_then(|tokens| Parser::new(tokens).parse())
+            .map_err(|e| e.in_expression(expression))?;
+        Ok(Self {
+            expression: expression.to_string(),
+            expr,
+        })
+    }
+}
+
+impl fmt::Display for DisplayFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+/// A syntax error, pointing at where in the expression it was found
+#[derive(Debug, Clone)]
+pub struct FilterError {
+    /// Byte offset in the expression
+    position: usize,
+    message: String,
+    expression: String,
+}
+
+impl FilterError {
+    fn new(position: usize, message: impl Into<String>) -> Self {
+        Self {
+            position,
+            message: message.into(),
+            expression: String::new(),
+        }
+    }
+
+    fn in_expression(self, expression: &str) -> Self {
+        Self {
+            expression: expression.to_string(),
+            ..self
+        }
+    }
+
+    /// Column of the error, counted in characters from 1
+    pub fn column(&self) -> usize {
+        self.expression[..self.position].chars().count() + 1
+    }
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "column {}: {}", self.column(), self.message)?;
+        writeln!(f, "  {}", self.expression)?;
+        write!(f, "  {}^", " ".repeat(self.column() - 1))
+    }
+}
+
+impl Error for FilterError {}
@@ -0,0 +1,226 @@
+use super::{
+    lexer::{CompareOp, Token, TokenKind},
+    Expr, Field, FieldKind, FilterError, Value,
+};
+use crate::{
+    capture::dns_providers::IpNetwork,
+    dns::{self, DnsProtocol},
+};
+
+const RCODES: [&str; 8] = [
+    "NOERROR",
+    "FORMERR",
+    "SERVFAIL",
+    "NXDOMAIN",
+    "NOTIMP",
+    "REFUSED",
+    "BADVERS",
+    "BADCOOKIE",
+];
+
+/// Recursive descent parser over the tokens of one expression. `||` binds
+/// looser than `&&`, which binds looser than `!`.
+pub struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self { tokens, pos: 0 }
+    }
+
+    /// Parse the whole expression, which must not be followed by anything
+    pub fn parse(mut self) -> Result<Expr, FilterError> {
+        let expr = self.or()?;
+        let token = self.peek();
+        if token.kind != TokenKind::End {
+            return Err(FilterError::new(
+                token.position,
+                format!("expected `&&` or `||`, found {}", token.kind.describe()),
+            ));
+        }
+        Ok(expr)
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        // Stay on the End token once reached
+        if token.kind != TokenKind::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.and()?;
+        while self.peek().kind == TokenKind::Or {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.unary()?;
+        while self.peek().kind == TokenKind::And {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, FilterError> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Not => Ok(Expr::Not(Box::new(self.unary()?))),
+            TokenKind::LParen => {
+                let expr = self.or()?;
+                let close = self.next();
+                if close.kind != TokenKind::RParen {
+                    return Err(FilterError::new(
+                        close.position,
+                        format!("expected `)`, found {}", close.kind.describe()),
+                    ));
+                }
+                Ok(expr)
+            }
+            TokenKind::Word(name) => self.comparison(&name, token.position),
+            kind => Err(FilterError::new(
+                token.position,
+                format!("expected a field name, found {}", kind.describe()),
+            )),
+        }
+    }
+
+    /// A comparison, or a flag on its own, starting with the field `name`
+    fn comparison(&mut self, name: &str, position: usize) -> Result<Expr, FilterError> {
+        let field = Field::from_name(name).ok_or_else(|| {
+            FilterError::new(
+                position,
+                format!(
+                    "unknown field `{}`, expected one of: {}",
+                    name,
+                    Field::ALL.map(|field| field.name()).join(", ")
+                ),
+            )
+        })?;
+
+        let op_token = self.peek().clone();
+        let TokenKind::Compare(op) = op_token.kind else {
+            if field.kind() == FieldKind::Flag {
+                return Ok(Expr::Flag(field));
+            }
+            return Err(FilterError::new(
+                op_token.position,
+                format!(
+                    "expected a comparison after `{}`, found {}",
+                    name,
+                    op_token.kind.describe()
+                ),
+            ));
+        };
+        self.next();
+        check_operator(field, op, op_token.position)?;
+
+        let value_token = self.next();
+        let text = match value_token.kind {
+            TokenKind::Word(text) | TokenKind::Str(text) => text,
+            kind => {
+                return Err(FilterError::new(
+                    value_token.position,
+                    format!(
+                        "expected a value after `{}`, found {}",
+                        op.as_str(),
+                        kind.describe()
+                    ),
+                ))
+            }
+        };
+        let value = parse_value(field, op, &text)
+            .map_err(|message| FilterError::new(value_token.position, message))?;
+
+        Ok(Expr::Compare { field, op, value })
+    }
+}
+
+fn check_operator(field: Field, op: CompareOp, position: usize) -> Result<(), FilterError> {
+    let allowed = match field.kind() {
+        FieldKind::Flag => false,
+        FieldKind::Number => !matches!(op, CompareOp::Matches | CompareOp::NotMatches),
+        FieldKind::Address => matches!(op, CompareOp::Eq | CompareOp::Ne),
+        FieldKind::Text | FieldKind::Keyword => matches!(
+            op,
+            CompareOp::Eq | CompareOp::Ne | CompareOp::Matches | CompareOp::NotMatches
+        ),
+    };
+    if allowed {
+        return Ok(());
+    }
+
+    let message = match field.kind() {
+        FieldKind::Flag => format!(
+            "`{0}` is a flag and can't be compared; use `{0}` or `!{0}`",
+            field.name()
+        ),
+        _ => format!("`{}` can't be used with `{}`", op.as_str(), field.name()),
+    };
+    Err(FilterError::new(position, message))
+}
+
+fn parse_value(field: Field, op: CompareOp, text: &str) -> Result<Value, String> {
+    match field.kind() {
+        FieldKind::Number => text
+            .parse()
+            .map(Value::Number)
+            .map_err(|_| format!("expected a number, found `{}`", text)),
+        FieldKind::Address => text.parse::<IpNetwork>().map(Value::Network),
+        FieldKind::Text | FieldKind::Keyword => {
+            // Names are compared without their trailing dot, as they're displayed
+            let text = match field {
+                Field::Qname => text.trim_end_matches('.'),
+                _ => text,
+            };
+            let exact = matches!(op, CompareOp::Eq | CompareOp::Ne);
+            if field.kind() == FieldKind::Keyword && exact && !is_known_keyword(field, text) {
+                return Err(format!("unknown {} `{}`", field.description(), text));
+            }
+            // Types are shown by mnemonic where they have one, so `TYPE1`
+            // is compared as `A`
+            if field == Field::Qtype
+                && exact
+                && let Some(code) = dns::type_code(text)
+            {
+                return Ok(Value::Text(dns::type_name(code).to_lowercase()));
+            }
+            Ok(Value::Text(text.to_lowercase()))
+        }
+        FieldKind::Flag => unreachable!("flags are never compared"),
+    }
+}
+
+/// Whether `text` is a value the keyword `field` can take, so that a typo
+/// isn't silently a filter that never matches
+fn is_known_keyword(field: Field, text: &str) -> bool {
+    let text = text.to_uppercase();
+    match field {
+        Field::Qtype => {
+            ["NB", "NBSTAT"].contains(&text.as_str()) || dns::type_code(&text).is_some()
+        }
+        Field::Rcode => {
+            RCODES.contains(&text.as_str())
+                || text
+                    .strip_prefix("RCODE")
+                    .is_some_and(|code| code.parse::<u16>().is_ok())
+        }
+        Field::Protocol => DnsProtocol::ALL
+            .iter()
+            .any(|protocol| protocol.as_str().eq_ignore_ascii_case(&text)),
+        Field::Transport => text == "UDP" || text == "TCP",
+        _ => true,
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, VecDeque},
+    net::IpAddr,
+    time::{Duration, SystemTime},
+};
+
+use super::{DnsPacket, DnsProtocol};
+
+/// Identifies a query so its response can be found: the client socket, the
+/// resolver it asked, the transaction ID and the (case-folded) question name
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+struct TransactionKey {
+    client: IpAddr,
+    client_port: u16,
+    /// None for LLMNR and NBNS, whose queries are multicast or broadcast and
+    /// may be answered by any host
+    resolver: Option<IpAddr>,
+    id: u16,
+    qname: String,
+}
+
+impl TransactionKey {
+    fn for_packet(packet: &DnsPacket) -> Option<Self> {
+        // mDNS answers are multicast to every listener, often answer several
+        // queries at once, and many queries rightly go unanswered
+        if packet.protocol == DnsProtocol::Mdns {
+            return None;
+        }
+
+        let qname = packet.question()?.name.to_lowercase();
+        let id = packet.header.id;
+        let key = if packet.header.is_response {
+            TransactionKey {
+                client: packet.destination,
+                client_port: packet.destination_port,
+                resolver: packet.protocol.is_unicast().then_some(packet.source),
+                id,
+                qname,
+            }
+        } else {
+            TransactionKey {
+                client: packet.source,
+                client_port: packet.source_port,
+                resolver: packet.protocol.is_unicast().then_some(packet.destination),
+                id,
+                qname,
+            }
+        };
+        Some(key)
+    }
+}
+
+/// A query matched with its response
+#[derive(Debug, Clone)]
+pub struct DnsTransaction {
+    pub query: DnsPacket,
+    pub response: DnsPacket,
+    /// Time between the query and the response being captured
+    pub rtt: Duration,
+}
+
+/// Matches responses to the queries that caused them, measuring resolver
+/// latency and flagging queries that go unanswered
+pub struct Correlator {
+    pending: HashMap<TransactionKey, DnsPacket>,
+    /// Queries in the order they were seen, for expiry
+    order: VecDeque<(SystemTime, TransactionKey)>,
+    timeout: Duration,
+}
+
+impl Correlator {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            pending: HashMap::new(),
+            order: VecDeque::new(),
+            timeout,
+        }
+    }
+
+    /// Record a query, or match a response against an earlier query
+    pub fn observe(&mut self, packet: &DnsPacket) -> Option<DnsTransaction> {
+        let key = TransactionKey::for_packet(packet)?;
+
+        if !packet.header.is_response {
+            // Keep the first copy of a retransmitted query so the RTT reflects
+            // how long the client actually waited
+            if let Entry::Vacant(entry) = self.pending.entry(key.clone()) {
+                entry.insert(packet.clone());
+                self.order.push_back((packet.timestamp, key));
+            }
+            return None;
+        }
+
+        let query = self.pending.remove(&key)?;
+        let rtt = packet
+            .timestamp
+            .duration_since(query.timestamp)
+            .unwrap_or_default();
+        Some(DnsTransaction {
+            query,
+            response: packet.clone(),
+            rtt,
+        })
+    }
+
+    /// Remove and return queries that have waited longer than the timeout as of `now`
+    pub fn expire(&mut self, now: SystemTime) -> Vec<DnsPacket> {
+        let mut expired = Vec::new();
+        while let Some((timestamp, key)) = self.order.pop_front() {
+            if now.duration_since(timestamp).unwrap_or_default() < self.timeout {
+                self.order.push_front((timestamp, key));
+                break;
+            }
+            // Skip queries already answered, or answered and asked again since
+            if self
+                .pending
+                .get(&key)
+                .is_some_and(|query| query.timestamp == timestamp)
+            {
+                expired.extend(self.pending.remove(&key));
+            }
+        }
+        expired
+    }
+
+    /// Remove and return every outstanding query, e.g. at the end of a savefile
+    pub fn drain(&mut self) -> Vec<DnsPacket> {
+        self.order.clear();
+        let mut remaining: Vec<DnsPacket> = self.pending.drain().map(|(_, query)| query).collect();
+        remaining.sort_by_key(|query| query.timestamp);
+        remaining
+    }
+}
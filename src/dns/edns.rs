@@ -0,0 +1,256 @@
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+// EDNS option codes (https://www.iana.org/assignments/dns-parameters)
+const OPTION_NSID: u16 = 3;
+const OPTION_CLIENT_SUBNET: u16 = 8;
+const OPTION_COOKIE: u16 = 10;
+const OPTION_TCP_KEEPALIVE: u16 = 11;
+const OPTION_PADDING: u16 = 12;
+const OPTION_EXTENDED_ERROR: u16 = 15;
+
+const FLAG_DNSSEC_OK: u16 = 0x8000;
+
+/// EDNS Client Subnet (RFC 7871)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientSubnet {
+    pub address: IpAddr,
+    pub source_prefix: u8,
+    pub scope_prefix: u8,
+}
+
+impl fmt::Display for ClientSubnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.source_prefix)?;
+        if self.scope_prefix != 0 {
+            write!(f, " (scope /{})", self.scope_prefix)?;
+        }
+        Ok(())
+    }
+}
+
+/// Extended DNS Error (RFC 8914)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedError {
+    pub code: u16,
+    pub text: String,
+}
+
+impl ExtendedError {
+    pub fn code_name(&self) -> &'static str {
+        match self.code {
+            0 => "Other Error",
+            1 => "Unsupported DNSKEY Algorithm",
+            2 => "Unsupported DS Digest Type",
+            3 => "Stale Answer",
+            4 => "Forged Answer",
+            5 => "DNSSEC Indeterminate",
+            6 => "DNSSEC Bogus",
+            7 => "Signature Expired",
+            8 => "Signature Not Yet Valid",
+            9 => "DNSKEY Missing",
+            10 => "RRSIGs Missing",
+            11 => "No Zone Key Bit Set",
+            12 => "NSEC Missing",
+            13 => "Cached Error",
+            14 => "Not Ready",
+            15 => "Blocked",
+            16 => "Censored",
+            17 => "Filtered",
+            18 => "Prohibited",
+            19 => "Stale NXDOMAIN Answer",
+            20 => "Not Authoritative",
+            21 => "Not Supported",
+            22 => "No Reachable Authority",
+            23 => "Network Error",
+            24 => "Invalid Data",
+            _ => "Unassigned",
+        }
+    }
+}
+
+impl fmt::Display for ExtendedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EDE {} ({})", self.code, self.code_name())?;
+        if !self.text.is_empty() {
+            write!(f, ": {}", self.text)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EdnsOption {
+    Nsid(Vec<u8>),
+    ClientSubnet(ClientSubnet),
+    /// DNS Cookies (RFC 7873); the server cookie is empty in a first query
+    Cookie {
+        client: Vec<u8>,
+        server: Vec<u8>,
+    },
+    /// edns-tcp-keepalive (RFC 7828), in units of 100ms when present
+    TcpKeepalive(Option<u16>),
+    /// Padding (RFC 7830), kept only as its length
+    Padding(usize),
+    ExtendedError(ExtendedError),
+    Other {
+        code: u16,
+        data: Vec<u8>,
+    },
+}
+
+impl fmt::Display for EdnsOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EdnsOption::Nsid(id) => write!(f, "NSID {}", String::from_utf8_lossy(id)),
+            EdnsOption::ClientSubnet(subnet) => write!(f, "ECS {}", subnet),
+            EdnsOption::Cookie { server, .. } if server.is_empty() => write!(f, "COOKIE (client)"),
+            EdnsOption::Cookie { .. } => write!(f, "COOKIE (client+server)"),
+            EdnsOption::TcpKeepalive(Some(timeout)) => {
+                write!(f, "KEEPALIVE {}ms", *timeout as u32 * 100)
+            }
+            EdnsOption::TcpKeepalive(None) => write!(f, "KEEPALIVE"),
+            EdnsOption::Padding(len) => write!(f, "PADDING {} bytes", len),
+            EdnsOption::ExtendedError(error) => write!(f, "{}", error),
+            EdnsOption::Other { code, data } => write!(f, "OPT{} {} bytes", code, data.len()),
+        }
+    }
+}
+
+/// The EDNS(0) OPT pseudo-record (RFC 6891)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edns {
+    pub udp_payload_size: u16,
+    /// Upper 8 bits of the 12 bit extended RCODE
+    pub extended_rcode: u8,
+    pub version: u8,
+    pub dnssec_ok: bool,
+    pub options: Vec<EdnsOption>,
+}
+
+impl Edns {
+    pub fn client_subnet(&self) -> Option<&ClientSubnet> {
+        self.options.iter().find_map(|option| match option {
+            EdnsOption::ClientSubnet(subnet) => Some(subnet),
+            _ => None,
+        })
+    }
+
+    pub fn extended_errors(&self) -> impl Iterator<Item = &ExtendedError> {
+        self.options.iter().filter_map(|option| match option {
+            EdnsOption::ExtendedError(error) => Some(error),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for Edns {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "version {}, udp {}", self.version, self.udp_payload_size)?;
+        if self.dnssec_ok {
+            write!(f, ", do")?;
+        }
+        for option in &self.options {
+            write!(f, ", {}", option)?;
+        }
+        Ok(())
+    }
+}
+
+impl Edns {
+    /// Decode an OPT pseudo-record, which carries the UDP payload size in its
+    /// class and the extended RCODE, version and flags in its TTL
+    pub(super) fn decode(class: u16, ttl: u32, rdata: &[u8]) -> Self {
+        let [extended_rcode, version, flags_high, flags_low] = ttl.to_be_bytes();
+        let flags = u16::from_be_bytes([flags_high, flags_low]);
+        Self {
+            udp_payload_size: class,
+            extended_rcode,
+            version,
+            dnssec_ok: flags & FLAG_DNSSEC_OK != 0,
+            options: parse_options(rdata),
+        }
+    }
+}
+
+/// Parse the {code, length, data} option list in OPT RDATA, stopping at the
+/// first truncated option
+fn parse_options(mut data: &[u8]) -> Vec<EdnsOption> {
+    let mut options = Vec::new();
+
+    while data.len() >= 4 {
+        let code = u16::from_be_bytes([data[0], data[1]]);
+        let len = u16::from_be_bytes([data[2], data[3]]) as usize;
+        let Some(value) = data.get(4..4 + len) else {
+            break;
+        };
+        data = &data[4 + len..];
+
+        let option = match code {
+            OPTION_NSID => EdnsOption::Nsid(value.to_vec()),
+            OPTION_CLIENT_SUBNET => match parse_client_subnet(value) {
+                Some(subnet) => EdnsOption::ClientSubnet(subnet),
+                None => EdnsOption::Other {
+                    code,
+                    data: value.to_vec(),
+                },
+            },
+            OPTION_COOKIE => {
+                let split = value.len().min(8);
+                EdnsOption::Cookie {
+                    client: value[..split].to_vec(),
+                    server: value[split..].to_vec(),
+                }
+            }
+            OPTION_TCP_KEEPALIVE => {
+                EdnsOption::TcpKeepalive(value.get(0..2).map(|v| u16::from_be_bytes([v[0], v[1]])))
+            }
+            OPTION_PADDING => EdnsOption::Padding(value.len()),
+            OPTION_EXTENDED_ERROR if value.len() >= 2 => EdnsOption::ExtendedError(ExtendedError {
+                code: u16::from_be_bytes([value[0], value[1]]),
+                text: String::from_utf8_lossy(&value[2..])
+                    .trim_end_matches('\0')
+                    .to_string(),
+            }),
+            _ => EdnsOption::Other {
+                code,
+                data: value.to_vec(),
+            },
+        };
+        options.push(option);
+    }
+
+    options
+}
+
+fn parse_client_subnet(value: &[u8]) -> Option<ClientSubnet> {
+    let family = u16::from_be_bytes([*value.first()?, *value.get(1)?]);
+    let source_prefix = *value.get(2)?;
+    let scope_prefix = *value.get(3)?;
+    // The address is truncated to the bytes covered by the source prefix
+    let address = value.get(4..)?;
+
+    let address = match family {
+        1 => {
+            let mut octets = [0u8; 4];
+            let len = address.len().min(4);
+            octets[..len].copy_from_slice(&address[..len]);
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        2 => {
+            let mut octets = [0u8; 16];
+            let len = address.len().min(16);
+            octets[..len].copy_from_slice(&address[..len]);
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return None,
+    };
+
+    Some(ClientSubnet {
+        address,
+        source_prefix,
+        scope_prefix,
+    })
+}
@@ -0,0 +1,326 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    mem,
+    net::{IpAddr, SocketAddr},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    capture::dns_providers::{get_provider_for_ip, DnsProvider},
+    net::{client_hello_server_name, DecodedPacket, TcpHeader, TransportLayer},
+};
+
+/// DNS over TLS (RFC 7858) on TCP and DNS over QUIC (RFC 9250) on UDP
+const DOT_DOQ_PORT: u16 = 853;
+const HTTPS_PORT: u16 = 443;
+/// Sessions that haven't seen a packet for this long are closed
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+/// How often to look for idle sessions and report sessions' traffic
+const EVICTION_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum EncryptedDnsProtocol {
+    Dot,
+    Doh,
+    Doq,
+}
+
+impl EncryptedDnsProtocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EncryptedDnsProtocol::Dot => "DoT",
+            EncryptedDnsProtocol::Doh => "DoH",
+            EncryptedDnsProtocol::Doq => "DoQ",
+        }
+    }
+}
+
+/// A connection carrying encrypted DNS. The queries inside can't be seen, so
+/// only its endpoints and volume are known.
+#[derive(Debug, Clone)]
+pub struct EncryptedDnsSession {
+    pub protocol: EncryptedDnsProtocol,
+    pub provider: DnsProvider,
+    pub client: SocketAddr,
+    pub server: SocketAddr,
+    /// TLS server name from the ClientHello, always present for DoH
+    pub server_name: Option<String>,
+    /// Interface the session was first seen on, None for savefiles
+    pub interface: Option<String>,
+    /// Packets and bytes (as seen on the wire) in both directions
+    pub packets: u64,
+    pub bytes: u64,
+    pub started: SystemTime,
+    pub last_seen: SystemTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    Opened,
+    /// Packets and bytes exchanged on the session since its last update,
+    /// reported at most once per eviction interval and when it closes
+    Traffic {
+        packets: u64,
+        bytes: u64,
+    },
+    /// The connection was closed or went idle
+    Closed,
+}
+
+/// A change to an encrypted DNS session, with the session as it is after it
+#[derive(Debug, Clone)]
+pub struct EncryptedDnsUpdate {
+    pub session: EncryptedDnsSession,
+    pub event: SessionEvent,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+struct SessionKey {
+    client: SocketAddr,
+    server: SocketAddr,
+    protocol: EncryptedDnsProtocol,
+}
+
+struct Session {
+    info: EncryptedDnsSession,
+    /// Traffic not yet reported in an update
+    unreported_packets: u64,
+    unreported_bytes: u64,
+    client_fin: bool,
+    server_fin: bool,
+}
+
+impl Session {
+    /// An update for the traffic since the last one, if there was any
+    fn report(&mut self) -> Option<EncryptedDnsUpdate> {
+        if self.unreported_packets == 0 {
+            return None;
+        }
+        let event = SessionEvent::Traffic {
+            packets: mem::take(&mut self.unreported_packets),
+            bytes: mem::take(&mut self.unreported_bytes),
+        };
+        Some(EncryptedDnsUpdate {
+            session: self.info.clone(),
+            event,
+        })
+    }
+}
+
+/// One packet's endpoints, oriented by which side is the server
+struct Flow<'a> {
+    key: SessionKey,
+    from_client: bool,
+    tcp: Option<&'a TcpHeader>,
+}
+
+impl<'a> Flow<'a> {
+    fn classify(
+        source: IpAddr,
+        destination: IpAddr,
+        transport: &'a TransportLayer,
+    ) -> Option<Self> {
+        let (source_port, destination_port, tcp) = match transport {
+            TransportLayer::Tcp(tcp) => (tcp.source_port, tcp.destination_port, Some(tcp)),
+            TransportLayer::Udp(udp) => (udp.source_port, udp.destination_port, None),
+            TransportLayer::Other { .. } => return None,
+        };
+
+        let protocol = |port| match (port, tcp.is_some()) {
+            (DOT_DOQ_PORT, true) => Some(EncryptedDnsProtocol::Dot),
+            (DOT_DOQ_PORT, false) => Some(EncryptedDnsProtocol::Doq),
+            (HTTPS_PORT, true) => Some(EncryptedDnsProtocol::Doh),
+            _ => None,
+        };
+
+        let source = SocketAddr::new(source, source_port);
+        let destination = SocketAddr::new(destination, destination_port);
+        let (protocol, client, server, from_client) =
+            if let Some(protocol) = protocol(destination_port) {
+                (protocol, source, destination, true)
+            } else {
+                (protocol(source_port)?, destination, source, false)
+            };
+
+        Some(Self {
+            key: SessionKey {
+                client,
+                server,
+                protocol,
+            },
+            from_client,
+            tcp,
+        })
+    }
+}
+
+/// Spots DoT, DoQ and DoH connections and keeps per-session packet and byte
+/// counts. DoT and DoQ are recognised by port. HTTPS is only counted as DoH
+/// from a ClientHello whose server name is a DoH endpoint in the provider
+/// catalog and whose destination is one of that provider's networks.
+#[derive(Default)]
+pub struct EncryptedDnsTracker {
+    sessions: HashMap<SessionKey, Session>,
+    last_eviction: Option<SystemTime>,
+}
+
+impl EncryptedDnsTracker {
+    /// Account for one packet, returning the changes to the session it belongs to
+    pub fn process(
+        &mut self,
+        packet: &DecodedPacket,
+        wire_len: u64,
+        interface: Option<&str>,
+        timestamp: SystemTime,
+    ) -> Vec<EncryptedDnsUpdate> {
+        let mut updates = self.expire(timestamp);
+
+        let Some(flow) = Flow::classify(
+            packet.network.source(),
+            packet.network.destination(),
+            &packet.transport,
+        ) else {
+            return updates;
+        };
+
+        let session = match self.sessions.entry(flow.key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let Some(info) = Self::open(&flow, packet.payload, interface, timestamp) else {
+                    return updates;
+                };
+                updates.push(EncryptedDnsUpdate {
+                    session: info.clone(),
+                    event: SessionEvent::Opened,
+                });
+                entry.insert(Session {
+                    info,
+                    unreported_packets: 0,
+                    unreported_bytes: 0,
+                    client_fin: false,
+                    server_fin: false,
+                })
+            }
+        };
+        session.info.packets += 1;
+        session.info.bytes += wire_len;
+        session.info.last_seen = timestamp;
+        session.unreported_packets += 1;
+        session.unreported_bytes += wire_len;
+
+        if let Some(tcp) = flow.tcp {
+            if tcp.fin {
+                if flow.from_client {
+                    session.client_fin = true;
+                } else {
+                    session.server_fin = true;
+                }
+            }
+            if tcp.rst || (session.client_fin && session.server_fin) {
+                updates.extend(self.close(&flow.key));
+            }
+        }
+
+        updates
+    }
+
+    /// Start a session for a packet not belonging to one, if it should open one
+    fn open(
+        flow: &Flow,
+        payload: &[u8],
+        interface: Option<&str>,
+        timestamp: SystemTime,
+    ) -> Option<EncryptedDnsSession> {
+        let key = &flow.key;
+        let (provider, server_name) = match key.protocol {
+            EncryptedDnsProtocol::Dot | EncryptedDnsProtocol::Doq => {
+                // Only a handshake or data opens a session, so that the last
+                // ACKs of a closed connection don't start a new one
+                let starts = !payload.is_empty() || flow.tcp.is_some_and(|tcp| tcp.syn && !tcp.rst);
+                if !starts {
+                    return None;
+                }
+                let server_name = flow
+                    .from_client
+                    .then(|| client_hello_server_name(payload))
+                    .flatten();
+                (get_provider_for_ip(key.server.ip()), server_name)
+            }
+            EncryptedDnsProtocol::Doh => {
+                if !flow.from_client {
+                    return None;
+                }
+                let server_name = client_hello_server_name(payload)?;
+                let provider = DnsProvider::for_doh_host(&server_name)
+                    .filter(|provider| provider.contains(key.server.ip()))?;
+                (provider, Some(server_name))
+            }
+        };
+
+        Some(EncryptedDnsSession {
+            protocol: key.protocol,
+            provider,
+            client: key.client,
+            server: key.server,
+            server_name,
+            interface: interface.map(str::to_string),
+            packets: 0,
+            bytes: 0,
+            started: timestamp,
+            last_seen: timestamp,
+        })
+    }
+
+    /// Remove a session, reporting its last traffic and then its closing
+    fn close(&mut self, key: &SessionKey) -> Vec<EncryptedDnsUpdate> {
+        let Some(mut session) = self.sessions.remove(key) else {
+            return Vec::new();
+        };
+        let mut updates: Vec<EncryptedDnsUpdate> = session.report().into_iter().collect();
+        updates.push(EncryptedDnsUpdate {
+            session: session.info,
+            event: SessionEvent::Closed,
+        });
+        updates
+    }
+
+    /// Report each session's traffic since its last update, and close sessions
+    /// that have been idle for longer than the timeout as of `now`
+    pub fn expire(&mut self, now: SystemTime) -> Vec<EncryptedDnsUpdate> {
+        let due = self
+            .last_eviction
+            .is_none_or(|last| now.duration_since(last).unwrap_or_default() >= EVICTION_INTERVAL);
+        if !due {
+            return Vec::new();
+        }
+        self.last_eviction = Some(now);
+
+        let mut updates: Vec<EncryptedDnsUpdate> = self
+            .sessions
+            .values_mut()
+            .filter_map(Session::report)
+            .collect();
+
+        let idle: Vec<SessionKey> = self
+            .sessions
+            .iter()
+            .filter(|(_, session)| {
+                now.duration_since(session.info.last_seen)
+                    .unwrap_or_default()
+                    >= SESSION_IDLE_TIMEOUT
+            })
+            .map(|(key, _)| *key)
+            .collect();
+        updates.extend(idle.iter().flat_map(|key| self.close(key)));
+        updates
+    }
+
+    /// Close every open session, e.g. at the end of a savefile
+    pub fn drain(&mut self) -> Vec<EncryptedDnsUpdate> {
+        let keys: Vec<SessionKey> = self.sessions.keys().copied().collect();
+        let mut closed: Vec<EncryptedDnsUpdate> =
+            keys.iter().flat_map(|key| self.close(key)).collect();
+        closed.sort_by_key(|update| update.session.started);
+        closed
+    }
+}
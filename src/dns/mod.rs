@@ -1,105 +1,388 @@
 use crate::{
+    attribution::ProcessInfo,
     capture::dns_providers::{get_provider_for_ip, DnsProvider},
-    net::extract_ip_addresses,
+    net::{self, ChecksumStatus, LinkLayer, TransportLayer},
 };
-use dns_parser::{Packet, QueryType, RData};
+use dns_parser::{Opcode, ResponseCode};
+use encrypted::EncryptedDnsTracker;
+use protocol::DNS_PORT;
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tcp::TcpReassembler;
 
-#[derive(Debug)]
-pub struct DnsQuery {
-    pub name: String,
-    pub query_type: QueryType,
+mod correlation;
+mod edns;
+mod encrypted;
+mod nbns;
+mod protocol;
+mod record;
+mod tcp;
+mod wire;
+
+pub use correlation::{Correlator, DnsTransaction};
+pub use edns::Edns;
+pub use encrypted::{EncryptedDnsSession, EncryptedDnsUpdate, SessionEvent};
+pub use protocol::DnsProtocol;
+pub use record::{type_code, type_name, DnsRecord};
+
+/// The type a question asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuestionType {
+    /// A DNS type code, including ones without a mnemonic
+    Dns(u16),
+    /// NetBIOS name query (NB)
+    NetbiosName,
+    /// NetBIOS node status query (NBSTAT)
+    NetbiosStatus,
+}
+
+impl fmt::Display for QuestionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuestionType::Dns(code) => write!(f, "{}", type_name(*code)),
+            QuestionType::NetbiosName => write!(f, "NB"),
+            QuestionType::NetbiosStatus => write!(f, "NBSTAT"),
+        }
+    }
 }
 
-#[derive(Debug)]
-pub struct DnsAnswer {
+#[derive(Debug, Clone)]
+pub struct DnsQuestion {
     pub name: String,
-    pub data: String,
+    pub query_type: QuestionType,
+    /// mDNS QU bit: the querier asks for a unicast rather than multicast reply
+    pub unicast_response: bool,
+}
+
+/// The fixed DNS header (RFC 1035 §4.1.1)
+#[derive(Debug, Clone)]
+pub struct DnsHeader {
+    /// Transaction ID shared by a query and its response
+    pub id: u16,
+    pub is_response: bool,
+    pub opcode: Opcode,
+    pub authoritative: bool,
+    pub truncated: bool,
+    pub recursion_desired: bool,
+    pub recursion_available: bool,
+    pub authenticated_data: bool,
+    pub checking_disabled: bool,
+    /// NBNS B flag: the message was broadcast rather than sent to a name server
+    pub broadcast: bool,
+    pub rcode: ResponseCode,
 }
 
-#[derive(Debug)]
+impl DnsHeader {
+    /// The mnemonic for the response code, e.g. NXDOMAIN
+    pub fn rcode_name(&self) -> String {
+        match self.rcode {
+            ResponseCode::NoError => "NOERROR".to_string(),
+            ResponseCode::FormatError => "FORMERR".to_string(),
+            ResponseCode::ServerFailure => "SERVFAIL".to_string(),
+            ResponseCode::NameError => "NXDOMAIN".to_string(),
+            ResponseCode::NotImplemented => "NOTIMP".to_string(),
+            ResponseCode::Refused => "REFUSED".to_string(),
+            ResponseCode::Reserved(code) => format!("RCODE{}", code),
+        }
+    }
+
+    /// The set flags in dig's notation, e.g. "qr rd ra"
+    pub fn flags(&self) -> String {
+        [
+            (self.is_response, "qr"),
+            (self.authoritative, "aa"),
+            (self.truncated, "tc"),
+            (self.recursion_desired, "rd"),
+            (self.recursion_available, "ra"),
+            (self.authenticated_data, "ad"),
+            (self.checking_disabled, "cd"),
+            (self.broadcast, "b"),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+}
+
+/// Transport a DNS message was carried over
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum DnsTransport {
+    Udp,
+    Tcp,
+}
+
+impl DnsTransport {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DnsTransport::Udp => "udp",
+            DnsTransport::Tcp => "tcp",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct DnsPacket {
-    pub query: Option<DnsQuery>,
-    pub answers: Vec<DnsAnswer>,
+    pub header: DnsHeader,
+    pub questions: Vec<DnsQuestion>,
+    pub answers: Vec<DnsRecord>,
+    pub authorities: Vec<DnsRecord>,
+    /// Additional records, not including the OPT pseudo-record
+    pub additionals: Vec<DnsRecord>,
+    /// EDNS(0) parameters, if the message carried an OPT record
+    pub edns: Option<Edns>,
+    pub protocol: DnsProtocol,
+    /// The resolver the message was exchanged with. Always unknown for the
+    /// link-local protocols, which have no resolver.
     pub provider: DnsProvider,
-    pub source: String,
-    pub destination: String,
+    /// Sent to or from a resolver other than the system's configured ones
+    pub bypass: bool,
+    pub source: IpAddr,
+    pub source_port: u16,
+    pub destination: IpAddr,
+    pub destination_port: u16,
+    pub transport: DnsTransport,
+    /// Result of validating the transport checksum
+    pub checksum: ChecksumStatus,
+    pub link: LinkLayer,
+    /// Interface the packet was captured on, None when read from a savefile
+    pub interface: Option<String>,
+    /// Local process that sent the query, filled in by the capture loop when
+    /// it can be found
+    pub process: Option<ProcessInfo>,
+    /// When the packet was captured, taken from the pcap header so that
+    /// savefiles keep their original timestamps
+    pub timestamp: SystemTime,
 }
 
-/// Parse a raw packet captured by pcap into a DNS packet
-pub fn parse_packet(packet: &pcap::Packet) -> Option<DnsPacket> {
-    // Skip Ethernet header (typically 14 bytes) and IP header (typically 20 bytes)
-    // to get to the UDP header (8 bytes), after which comes the DNS data
-    let dns_data_start = 42; // 14 (Ethernet) + 20 (IP) + 8 (UDP)
+impl DnsPacket {
+    /// The first question, which is the only one in practically all traffic
+    pub fn question(&self) -> Option<&DnsQuestion> {
+        self.questions.first()
+    }
+
+    /// The mnemonic for the response code, including the upper bits carried
+    /// in the OPT record, e.g. BADVERS
+    pub fn rcode_name(&self) -> String {
+        let extended = self.edns.as_ref().map_or(0, |edns| edns.extended_rcode);
+        if extended == 0 {
+            return self.header.rcode_name();
+        }
 
-    if packet.data.len() <= dns_data_start {
-        return None; // Packet too small to contain DNS data
+        let low: u8 = self.header.rcode.into();
+        match (extended as u16) << 4 | low as u16 {
+            16 => "BADVERS".to_string(),
+            23 => "BADCOOKIE".to_string(),
+            code => format!("RCODE{}", code),
+        }
     }
-    let (source, destination) = extract_ip_addresses(packet.data);
-    let provider = get_provider_for_ip(&source);
-
-    // Parse DNS packet
-    match Packet::parse(&packet.data[dns_data_start..]) {
-        Ok(dns) => {
-            // Extract query
-            let query = if dns.questions.len() > 0 {
-                let q = &dns.questions[0];
-                Some(DnsQuery {
-                    name: q.qname.to_string(),
-                    query_type: q.qtype,
-                })
-            } else {
-                None
-            };
-
-            // Extract answers
-            let answers = dns
-                .answers
-                .iter()
-                .map(|answer| {
-                    let data = match &answer.data {
-                        RData::A(addr) => addr.0.to_string(),
-                        RData::AAAA(addr) => addr.0.to_string(),
-                        RData::CNAME(name) => name.to_string(),
-                        RData::MX(mx) => format!("{} {}", mx.preference, mx.exchange),
-                        RData::NS(name) => name.to_string(),
-                        RData::PTR(name) => name.to_string(),
-                        RData::SOA(soa) => format!(
-                            "{} {} {} {} {} {} {}",
-                            soa.primary_ns,
-                            soa.mailbox,
-                            soa.serial,
-                            soa.refresh,
-                            soa.retry,
-                            soa.expire,
-                            soa.minimum_ttl
-                        ),
-                        RData::SRV(srv) => format!(
-                            "{} {} {} {}",
-                            srv.priority, srv.weight, srv.port, srv.target
-                        ),
-                        RData::TXT(txt) => txt
-                            .iter()
-                            .map(|chunk| String::from_utf8_lossy(chunk).to_string())
-                            .collect::<Vec<_>>()
-                            .join(""),
-                        _ => format!("<unsupported record type>"),
-                    };
-
-                    DnsAnswer {
-                        name: answer.name.to_string(),
-                        data,
-                    }
-                })
-                .collect();
-
-            Some(DnsPacket {
-                query,
-                answers,
-                provider,
-                source,
-                destination,
-            })
+}
+
+/// Something the decoder found in a packet
+#[derive(Debug, Clone)]
+pub enum DnsEvent {
+    /// A cleartext DNS message
+    Message(DnsPacket),
+    /// Activity on an encrypted DNS session
+    Encrypted(EncryptedDnsUpdate),
+}
+
+/// The decoded contents of a message, before it's placed in its envelope
+struct Message {
+    header: DnsHeader,
+    questions: Vec<DnsQuestion>,
+    answers: Vec<DnsRecord>,
+    authorities: Vec<DnsRecord>,
+    additionals: Vec<DnsRecord>,
+    edns: Option<Edns>,
+}
+
+/// Where a DNS message was seen, shared by every message in a packet or stream
+struct Envelope {
+    protocol: DnsProtocol,
+    source: IpAddr,
+    source_port: u16,
+    destination: IpAddr,
+    destination_port: u16,
+    transport: DnsTransport,
+    checksum: ChecksumStatus,
+    link: LinkLayer,
+    interface: Option<String>,
+    timestamp: SystemTime,
+}
+
+/// Decodes DNS messages from captured packets, keeping the state needed to
+/// follow DNS-over-TCP streams across segments and encrypted DNS sessions
+#[derive(Default)]
+pub struct DnsDecoder {
+    tcp: TcpReassembler,
+    encrypted: EncryptedDnsTracker,
+}
+
+impl DnsDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a raw packet captured by pcap on a link of type `linktype` into the
+    /// DNS messages it carries (or completes, for TCP), or the encrypted DNS
+    /// session activity it represents
+    pub fn parse_packet(
+        &mut self,
+        packet: &pcap::Packet,
+        linktype: pcap::Linktype,
+        interface: Option<&str>,
+    ) -> Vec<DnsEvent> {
+        let Some(decoded) = net::decode_packet(linktype, packet.data) else {
+            return Vec::new();
+        };
+
+        let timestamp = packet_timestamp(packet.header);
+
+        let updates =
+            self.encrypted
+                .process(&decoded, packet.header.len as u64, interface, timestamp);
+        let mut events: Vec<DnsEvent> = updates.into_iter().map(DnsEvent::Encrypted).collect();
+        events.extend(
+            self.parse_messages(&decoded, interface, timestamp)
+                .into_iter()
+                .map(DnsEvent::Message),
+        );
+        events
+    }
+
+    /// Close encrypted DNS sessions that have gone idle as of `now`
+    pub fn expire_sessions(&mut self, now: SystemTime) -> Vec<EncryptedDnsUpdate> {
+        self.encrypted.expire(now)
+    }
+
+    /// Close every open encrypted DNS session
+    pub fn close_sessions(&mut self) -> Vec<EncryptedDnsUpdate> {
+        self.encrypted.drain()
+    }
+
+    fn parse_messages(
+        &mut self,
+        decoded: &net::DecodedPacket,
+        interface: Option<&str>,
+        timestamp: SystemTime,
+    ) -> Vec<DnsPacket> {
+        let source = decoded.network.source();
+        let destination = decoded.network.destination();
+
+        match &decoded.transport {
+            TransportLayer::Udp(udp) => {
+                let Some(protocol) =
+                    DnsProtocol::from_udp_ports(udp.source_port, udp.destination_port)
+                else {
+                    return Vec::new();
+                };
+                let envelope = Envelope {
+                    protocol,
+                    source,
+                    source_port: udp.source_port,
+                    destination,
+                    destination_port: udp.destination_port,
+                    transport: DnsTransport::Udp,
+                    checksum: udp.checksum,
+                    link: decoded.link.clone(),
+                    interface: interface.map(str::to_string),
+                    timestamp,
+                };
+                parse_message(decoded.payload, &envelope)
+                    .into_iter()
+                    .collect()
+            }
+            // Of the protocols here, only plain DNS is carried over TCP
+            TransportLayer::Tcp(tcp)
+                if tcp.source_port == DNS_PORT || tcp.destination_port == DNS_PORT =>
+            {
+                let messages = self.tcp.process(
+                    SocketAddr::new(source, tcp.source_port),
+                    SocketAddr::new(destination, tcp.destination_port),
+                    tcp,
+                    decoded.payload,
+                    timestamp,
+                );
+                let envelope = Envelope {
+                    protocol: DnsProtocol::Dns,
+                    source,
+                    source_port: tcp.source_port,
+                    destination,
+                    destination_port: tcp.destination_port,
+                    transport: DnsTransport::Tcp,
+                    checksum: ChecksumStatus::Unverified,
+                    link: decoded.link.clone(),
+                    interface: interface.map(str::to_string),
+                    timestamp,
+                };
+                messages
+                    .iter()
+                    .filter_map(|message| parse_message(message, &envelope))
+                    .collect()
+            }
+            _ => Vec::new(),
         }
-        Err(_) => None, // Not a valid DNS packet
     }
 }
+
+/// Parse a single DNS message (without any TCP length prefix)
+fn parse_message(data: &[u8], envelope: &Envelope) -> Option<DnsPacket> {
+    let message = match envelope.protocol {
+        DnsProtocol::Nbns => nbns::parse(data)?,
+        _ => wire::parse(data)?,
+    };
+
+    let (provider, bypass) = if envelope.protocol.is_unicast() {
+        // The resolver is the destination of a query and the source of a response
+        let (resolver, client) = if message.header.is_response {
+            (envelope.source, envelope.destination)
+        } else {
+            (envelope.destination, envelope.source)
+        };
+        // Fall back to the client side so that traffic a known resolver
+        // exchanges with upstream servers is attributed to it as well
+        let provider = match get_provider_for_ip(resolver) {
+            DnsProvider::UNKNOWN => get_provider_for_ip(client),
+            provider => provider,
+        };
+        // Anything not exchanged with the configured resolvers goes around them
+        let bypass = DnsProvider::system().is_some_and(|system| provider != system);
+        (provider, bypass)
+    } else {
+        (DnsProvider::UNKNOWN, false)
+    };
+
+    Some(DnsPacket {
+        header: message.header,
+        questions: message.questions,
+        answers: message.answers,
+        authorities: message.authorities,
+        additionals: message.additionals,
+        edns: message.edns,
+        protocol: envelope.protocol,
+        provider,
+        bypass,
+        source: envelope.source,
+        source_port: envelope.source_port,
+        destination: envelope.destination,
+        destination_port: envelope.destination_port,
+        transport: envelope.transport,
+        checksum: envelope.checksum,
+        link: envelope.link.clone(),
+        interface: envelope.interface.clone(),
+        process: None,
+        timestamp: envelope.timestamp,
+    })
+}
+
+/// Capture time recorded in a pcap packet header
+pub fn packet_timestamp(header: &pcap::PacketHeader) -> SystemTime {
+    let secs = header.ts.tv_sec.max(0) as u64;
+    let micros = header.ts.tv_usec.max(0) as u64;
+    UNIX_EPOCH + Duration::from_secs(secs) + Duration::from_micros(micros)
+}
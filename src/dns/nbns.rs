@@ -0,0 +1,198 @@
+use std::net::Ipv4Addr;
+
+use dns_parser::{Opcode, ResponseCode};
+
+use super::{
+    record::RecordData, wire::Reader, DnsHeader, DnsQuestion, DnsRecord, Message, QuestionType,
+};
+
+// NBNS reuses the DNS layout (RFC 1002 §4.2) but with its own record types,
+// which dns_parser rejects, so it gets a decoder of its own
+const TYPE_A: u16 = 0x01;
+const TYPE_NB: u16 = 0x20;
+const TYPE_NBSTAT: u16 = 0x21;
+
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_AUTHORITATIVE: u16 = 0x0400;
+const FLAG_TRUNCATED: u16 = 0x0200;
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+const FLAG_RECURSION_AVAILABLE: u16 = 0x0080;
+const FLAG_BROADCAST: u16 = 0x0010;
+
+/// A NetBIOS name is 16 bytes, encoded as a 32 character label
+const ENCODED_NAME_LEN: usize = 32;
+/// Each entry of a node status response: a 15 byte name, suffix and flags
+const NODE_NAME_LEN: usize = 18;
+/// Limit on compression pointers followed for one name, to stop loops
+const MAX_POINTERS: usize = 16;
+
+impl Reader<'_> {
+    /// Read a possibly compressed name: the NetBIOS name followed by its scope
+    fn name(&mut self) -> Option<String> {
+        let mut labels = Vec::new();
+        let mut pos = self.pos;
+        let mut pointers = 0;
+        let mut end = None;
+
+        loop {
+            let len = *self.data.get(pos)? as usize;
+            if len & 0xc0 == 0xc0 {
+                let target = (len & 0x3f) << 8 | *self.data.get(pos + 1)? as usize;
+                end.get_or_insert(pos + 2);
+                pointers += 1;
+                if pointers > MAX_POINTERS {
+                    return None;
+                }
+                pos = target;
+                continue;
+            }
+            if len == 0 {
+                end.get_or_insert(pos + 1);
+                break;
+            }
+            labels.push(self.data.get(pos + 1..pos + 1 + len)?);
+            pos += 1 + len;
+        }
+        self.pos = end?;
+
+        let (first, scope) = labels.split_first()?;
+        let mut name = decode_netbios_name(first)
+            .unwrap_or_else(|| String::from_utf8_lossy(first).to_string());
+        for label in scope {
+            name.push('.');
+            name.push_str(&String::from_utf8_lossy(label));
+        }
+        Some(name)
+    }
+}
+
+/// Decode a first-level encoded name (RFC 1001 §14.1), shown as the name
+/// followed by its suffix byte in hex, e.g. `WORKGROUP<1d>`
+fn decode_netbios_name(label: &[u8]) -> Option<String> {
+    if label.len() != ENCODED_NAME_LEN {
+        return None;
+    }
+    let bytes = label
+        .chunks(2)
+        .map(|pair| {
+            let high = pair[0].checked_sub(b'A').filter(|n| *n < 16)?;
+            let low = pair[1].checked_sub(b'A').filter(|n| *n < 16)?;
+            Some(high << 4 | low)
+        })
+        .collect::<Option<Vec<u8>>>()?;
+    Some(format_netbios_name(&bytes))
+}
+
+/// Format a raw 16 byte NetBIOS name: 15 padded characters and a suffix
+fn format_netbios_name(bytes: &[u8]) -> String {
+    let (name, suffix) = bytes.split_at(15);
+    format!(
+        "{}<{:02x}>",
+        String::from_utf8_lossy(name).trim_end_matches([' ', '\0']),
+        suffix[0]
+    )
+}
+
+fn parse_question(reader: &mut Reader) -> Option<DnsQuestion> {
+    let name = reader.name()?;
+    let query_type = match reader.u16()? {
+        TYPE_NB => QuestionType::NetbiosName,
+        TYPE_NBSTAT => QuestionType::NetbiosStatus,
+        code => QuestionType::Dns(code),
+    };
+    reader.u16()?; // Class, always IN
+    Some(DnsQuestion {
+        name,
+        query_type,
+        unicast_response: false,
+    })
+}
+
+fn parse_record(reader: &mut Reader) -> Option<DnsRecord> {
+    let name = reader.name()?;
+    let record_type = reader.u16()?;
+    reader.u16()?; // Class, always IN
+    let ttl = reader.u32()?;
+    let len = reader.u16()? as usize;
+    let rdata = reader.bytes(len)?;
+
+    let data = match record_type {
+        // Each address is preceded by two bytes of flags (group bit, node type)
+        TYPE_NB => RecordData::Nb(
+            rdata
+                .chunks_exact(6)
+                .map(|entry| Ipv4Addr::new(entry[2], entry[3], entry[4], entry[5]))
+                .collect(),
+        ),
+        TYPE_NBSTAT => {
+            let count = *rdata.first()? as usize;
+            RecordData::Nbstat(
+                rdata[1..]
+                    .chunks_exact(NODE_NAME_LEN)
+                    .take(count)
+                    .map(|entry| format_netbios_name(&entry[..16]))
+                    .collect(),
+            )
+        }
+        TYPE_A if rdata.len() == 4 => {
+            RecordData::A(Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3]))
+        }
+        _ => RecordData::Unknown {
+            record_type,
+            data: rdata.to_vec(),
+        },
+    };
+
+    Some(DnsRecord {
+        name,
+        ttl,
+        data,
+        cache_flush: false,
+    })
+}
+
+/// Parse a NetBIOS Name Service message
+pub fn parse(data: &[u8]) -> Option<Message> {
+    let mut reader = Reader::new(data);
+    let id = reader.u16()?;
+    let flags = reader.u16()?;
+    let question_count = reader.u16()?;
+    let answer_count = reader.u16()?;
+    let authority_count = reader.u16()?;
+    let additional_count = reader.u16()?;
+
+    let header = DnsHeader {
+        id,
+        is_response: flags & FLAG_RESPONSE != 0,
+        opcode: Opcode::from(flags >> 11 & 0xf),
+        authoritative: flags & FLAG_AUTHORITATIVE != 0,
+        truncated: flags & FLAG_TRUNCATED != 0,
+        recursion_desired: flags & FLAG_RECURSION_DESIRED != 0,
+        recursion_available: flags & FLAG_RECURSION_AVAILABLE != 0,
+        authenticated_data: false,
+        checking_disabled: false,
+        broadcast: flags & FLAG_BROADCAST != 0,
+        rcode: ResponseCode::from((flags & 0xf) as u8),
+    };
+
+    let questions = (0..question_count)
+        .map(|_| parse_question(&mut reader))
+        .collect::<Option<Vec<_>>>()?;
+    let mut records = |count: u16| {
+        (0..count)
+            .map(|_| parse_record(&mut reader))
+            .collect::<Option<Vec<_>>>()
+    };
+    let answers = records(answer_count)?;
+    let authorities = records(authority_count)?;
+    let additionals = records(additional_count)?;
+
+    Some(Message {
+        header,
+        questions,
+        answers,
+        authorities,
+        additionals,
+        edns: None,
+    })
+}
@@ -0,0 +1,80 @@
+use std::fmt;
+
+use clap::ValueEnum;
+
+pub const DNS_PORT: u16 = 53;
+/// Multicast DNS (RFC 6762)
+const MDNS_PORT: u16 = 5353;
+/// Link-Local Multicast Name Resolution (RFC 4795)
+const LLMNR_PORT: u16 = 5355;
+/// NetBIOS Name Service (RFC 1002)
+const NBNS_PORT: u16 = 137;
+
+/// The name resolution protocol a message belongs to. All of them share the
+/// DNS message layout and are told apart by port.
+#[derive(ValueEnum, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum DnsProtocol {
+    /// Unicast DNS to a resolver
+    Dns,
+    /// Multicast DNS, e.g. Bonjour and Avahi
+    Mdns,
+    /// Link-Local Multicast Name Resolution, used by Windows
+    Llmnr,
+    /// NetBIOS Name Service, used by Windows and Samba
+    Nbns,
+}
+
+impl DnsProtocol {
+    pub const ALL: [DnsProtocol; 4] = [
+        DnsProtocol::Dns,
+        DnsProtocol::Mdns,
+        DnsProtocol::Llmnr,
+        DnsProtocol::Nbns,
+    ];
+
+    /// Identifier used on the command line and in JSON output
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DnsProtocol::Dns => "dns",
+            DnsProtocol::Mdns => "mdns",
+            DnsProtocol::Llmnr => "llmnr",
+            DnsProtocol::Nbns => "nbns",
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        match self {
+            DnsProtocol::Dns => DNS_PORT,
+            DnsProtocol::Mdns => MDNS_PORT,
+            DnsProtocol::Llmnr => LLMNR_PORT,
+            DnsProtocol::Nbns => NBNS_PORT,
+        }
+    }
+
+    /// The protocol of a UDP datagram between these ports, if any
+    pub fn from_udp_ports(source_port: u16, destination_port: u16) -> Option<Self> {
+        // Plain DNS first, so a query that happens to leave from an ephemeral
+        // port like 5353 is still read as DNS
+        DnsProtocol::ALL
+            .into_iter()
+            .find(|protocol| destination_port == protocol.port() || source_port == protocol.port())
+    }
+
+    /// Whether a query is answered by a single resolver. The link-local
+    /// protocols multicast or broadcast queries for any host to answer.
+    pub fn is_unicast(&self) -> bool {
+        *self == DnsProtocol::Dns
+    }
+}
+
+impl fmt::Display for DnsProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DnsProtocol::Dns => "DNS",
+            DnsProtocol::Mdns => "mDNS",
+            DnsProtocol::Llmnr => "LLMNR",
+            DnsProtocol::Nbns => "NBNS",
+        };
+        write!(f, "{}", name)
+    }
+}
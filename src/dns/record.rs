@@ -0,0 +1,256 @@
+use std::{
+    borrow::Cow,
+    fmt,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+use dns_parser::{RData, Type};
+
+/// Mnemonics for the record and query types, by type code
+/// (https://www.iana.org/assignments/dns-parameters)
+const TYPE_NAMES: [(u16, &str); 39] = [
+    (1, "A"),
+    (2, "NS"),
+    (3, "MD"),
+    (4, "MF"),
+    (5, "CNAME"),
+    (6, "SOA"),
+    (7, "MB"),
+    (8, "MG"),
+    (9, "MR"),
+    (10, "NULL"),
+    (11, "WKS"),
+    (12, "PTR"),
+    (13, "HINFO"),
+    (14, "MINFO"),
+    (15, "MX"),
+    (16, "TXT"),
+    (28, "AAAA"),
+    (33, "SRV"),
+    (35, "NAPTR"),
+    (39, "DNAME"),
+    (41, "OPT"),
+    (43, "DS"),
+    (46, "RRSIG"),
+    (47, "NSEC"),
+    (48, "DNSKEY"),
+    (50, "NSEC3"),
+    (51, "NSEC3PARAM"),
+    (52, "TLSA"),
+    (59, "CDS"),
+    (60, "CDNSKEY"),
+    (64, "SVCB"),
+    (65, "HTTPS"),
+    (99, "SPF"),
+    (251, "IXFR"),
+    (252, "AXFR"),
+    (253, "MAILB"),
+    (254, "MAILA"),
+    (255, "ANY"),
+    (257, "CAA"),
+];
+
+/// The mnemonic for a record or query type, or `TYPE<n>` for a type without
+/// one (RFC 3597 §5)
+pub fn type_name(code: u16) -> Cow<'static, str> {
+    TYPE_NAMES
+        .iter()
+        .find(|(known, _)| *known == code)
+        .map_or_else(
+            || Cow::Owned(format!("TYPE{}", code)),
+            |(_, name)| Cow::Borrowed(*name),
+        )
+}
+
+/// The type code for a mnemonic or `TYPE<n>`, ignoring case
+pub fn type_code(name: &str) -> Option<u16> {
+    let name = name.to_uppercase();
+    TYPE_NAMES
+        .iter()
+        .find(|(_, known)| *known == name)
+        .map(|(code, _)| *code)
+        .or_else(|| name.strip_prefix("TYPE")?.parse().ok())
+}
+
+/// Decoded data of a resource record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Cname(String),
+    Mx {
+        preference: u16,
+        exchange: String,
+    },
+    Ns(String),
+    Ptr(String),
+    Soa {
+        primary_ns: String,
+        mailbox: String,
+        serial: u32,
+        refresh: u32,
+        retry: u32,
+        expire: u32,
+        minimum_ttl: u32,
+    },
+    Srv {
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: String,
+    },
+    Txt(Vec<String>),
+    /// NetBIOS name addresses
+    Nb(Vec<Ipv4Addr>),
+    /// Names registered on a NetBIOS node, from a node status response
+    Nbstat(Vec<String>),
+    /// A record type we don't decode, kept as its type code and raw RDATA
+    Unknown {
+        record_type: u16,
+        data: Vec<u8>,
+    },
+}
+
+impl RecordData {
+    /// Mnemonic of the record type, or `TYPE<n>` for an undecoded type
+    /// without one
+    pub fn type_name(&self) -> Cow<'static, str> {
+        let name = match self {
+            RecordData::A(_) => "A",
+            RecordData::Aaaa(_) => "AAAA",
+            RecordData::Cname(_) => "CNAME",
+            RecordData::Mx { .. } => "MX",
+            RecordData::Ns(_) => "NS",
+            RecordData::Ptr(_) => "PTR",
+            RecordData::Soa { .. } => "SOA",
+            RecordData::Srv { .. } => "SRV",
+            RecordData::Txt(_) => "TXT",
+            RecordData::Nb(_) => "NB",
+            RecordData::Nbstat(_) => "NBSTAT",
+            RecordData::Unknown { record_type, .. } => return type_name(*record_type),
+        };
+        Cow::Borrowed(name)
+    }
+
+    /// Decode the RDATA of a record of type `record_type` in `message`. Types
+    /// dns_parser doesn't decode, and data it can't, are kept raw.
+    pub(super) fn decode(record_type: u16, rdata: &[u8], message: &[u8]) -> Self {
+        let decoded = Type::parse(record_type)
+            .ok()
+            .and_then(|known| RData::parse(known, rdata, message).ok());
+        let Some(data) = decoded else {
+            return RecordData::Unknown {
+                record_type,
+                data: rdata.to_vec(),
+            };
+        };
+
+        match data {
+            RData::A(addr) => RecordData::A(addr.0),
+            RData::AAAA(addr) => RecordData::Aaaa(addr.0),
+            RData::CNAME(name) => RecordData::Cname(name.to_string()),
+            RData::MX(mx) => RecordData::Mx {
+                preference: mx.preference,
+                exchange: mx.exchange.to_string(),
+            },
+            RData::NS(name) => RecordData::Ns(name.to_string()),
+            RData::PTR(name) => RecordData::Ptr(name.to_string()),
+            RData::SOA(soa) => RecordData::Soa {
+                primary_ns: soa.primary_ns.to_string(),
+                mailbox: soa.mailbox.to_string(),
+                serial: soa.serial,
+                refresh: soa.refresh,
+                retry: soa.retry,
+                expire: soa.expire,
+                minimum_ttl: soa.minimum_ttl,
+            },
+            RData::SRV(srv) => RecordData::Srv {
+                priority: srv.priority,
+                weight: srv.weight,
+                port: srv.port,
+                target: srv.target.to_string(),
+            },
+            RData::TXT(txt) => RecordData::Txt(
+                txt.iter()
+                    .map(|chunk| String::from_utf8_lossy(chunk).to_string())
+                    .collect(),
+            ),
+            RData::Unknown(bytes) => RecordData::Unknown {
+                record_type,
+                data: bytes.to_vec(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for RecordData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordData::A(addr) => write!(f, "{}", addr),
+            RecordData::Aaaa(addr) => write!(f, "{}", addr),
+            RecordData::Cname(name) | RecordData::Ns(name) | RecordData::Ptr(name) => {
+                write!(f, "{}", name)
+            }
+            RecordData::Mx {
+                preference,
+                exchange,
+            } => write!(f, "{} {}", preference, exchange),
+            RecordData::Soa {
+                primary_ns,
+                mailbox,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum_ttl,
+            } => write!(
+                f,
+                "{} {} {} {} {} {} {}",
+                primary_ns, mailbox, serial, refresh, retry, expire, minimum_ttl
+            ),
+            RecordData::Srv {
+                priority,
+                weight,
+                port,
+                target,
+            } => write!(f, "{} {} {} {}", priority, weight, port, target),
+            RecordData::Txt(chunks) => write!(f, "{}", chunks.join("")),
+            RecordData::Nb(addrs) => {
+                let addrs: Vec<String> = addrs.iter().map(|addr| addr.to_string()).collect();
+                write!(f, "{}", addrs.join(" "))
+            }
+            RecordData::Nbstat(names) => write!(f, "{}", names.join(" ")),
+            RecordData::Unknown { data, .. } => {
+                write!(f, "<unsupported record type, {} bytes>", data.len())
+            }
+        }
+    }
+}
+
+/// A resource record from the answer, authority or additional section
+#[derive(Debug, Clone)]
+pub struct DnsRecord {
+    pub name: String,
+    pub ttl: u32,
+    pub data: RecordData,
+    /// mDNS cache-flush bit: this record replaces any cached records of the
+    /// same name and type rather than adding to them
+    pub cache_flush: bool,
+}
+
+impl fmt::Display for DnsRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.name,
+            self.ttl,
+            self.data.type_name(),
+            self.data
+        )?;
+        if self.cache_flush {
+            write!(f, " (cache flush)")?;
+        }
+        Ok(())
+    }
+}
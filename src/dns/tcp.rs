@@ -0,0 +1,184 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    time::{Duration, SystemTime},
+};
+
+use crate::net::TcpHeader;
+
+/// Streams that haven't seen a segment for this long are dropped
+const STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Upper bound on out-of-order segments held per stream
+const MAX_PENDING_SEGMENTS: usize = 64;
+
+/// One direction of a TCP connection
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+struct FlowKey {
+    source: SocketAddr,
+    destination: SocketAddr,
+}
+
+#[derive(Debug)]
+struct Stream {
+    /// Sequence number the stream was picked up at
+    initial_sequence: u32,
+    /// Sequence number of the next in-order byte we expect
+    next_sequence: u32,
+    /// In-order bytes not yet consumed as a complete message
+    buffer: Vec<u8>,
+    /// Segments that arrived ahead of `next_sequence`, keyed by their offset
+    /// from `initial_sequence` so the order survives sequence wraparound
+    pending: BTreeMap<u32, Vec<u8>>,
+    last_seen: SystemTime,
+}
+
+impl Stream {
+    fn new(next_sequence: u32, timestamp: SystemTime) -> Self {
+        Self {
+            initial_sequence: next_sequence,
+            next_sequence,
+            buffer: Vec::new(),
+            pending: BTreeMap::new(),
+            last_seen: timestamp,
+        }
+    }
+
+    /// Add a segment's payload, returning false if too many segments are
+    /// waiting on a missing one and the stream should be dropped
+    fn push(&mut self, sequence: u32, payload: &[u8]) -> bool {
+        // Signed distance from where we expect the next byte, handling wraparound
+        let offset = sequence.wrapping_sub(self.next_sequence) as i32;
+
+        if offset > 0 {
+            if self.pending.len() >= MAX_PENDING_SEGMENTS {
+                return false;
+            }
+            self.pending.insert(
+                sequence.wrapping_sub(self.initial_sequence),
+                payload.to_vec(),
+            );
+            return true;
+        }
+
+        // Retransmission or overlap: skip the bytes we already have
+        let overlap = offset.unsigned_abs() as usize;
+        if overlap < payload.len() {
+            self.append(&payload[overlap..]);
+        }
+
+        // Fill in any queued segments that are now contiguous
+        while let Some(entry) = self.pending.first_entry() {
+            let sequence = self.initial_sequence.wrapping_add(*entry.key());
+            let offset = sequence.wrapping_sub(self.next_sequence) as i32;
+            if offset > 0 {
+                break;
+            }
+            let segment = entry.remove();
+            let overlap = offset.unsigned_abs() as usize;
+            if overlap < segment.len() {
+                self.append(&segment[overlap..]);
+            }
+        }
+
+        true
+    }
+
+    fn append(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+        self.next_sequence = self.next_sequence.wrapping_add(bytes.len() as u32);
+    }
+
+    /// Pop every complete length-prefixed message from the buffer. What's left
+    /// is part of one message, so at most its 2 byte prefix and 64KiB.
+    fn drain_messages(&mut self) -> Vec<Vec<u8>> {
+        let mut messages = Vec::new();
+        let mut consumed = 0;
+
+        while let Some(prefix) = self.buffer.get(consumed..consumed + 2) {
+            let len = u16::from_be_bytes([prefix[0], prefix[1]]) as usize;
+            let start = consumed + 2;
+            let Some(message) = self.buffer.get(start..start + len) else {
+                break;
+            };
+            messages.push(message.to_vec());
+            consumed = start + len;
+        }
+
+        self.buffer.drain(..consumed);
+        messages
+    }
+}
+
+/// Reassembles DNS messages sent over TCP, where each message is preceded by
+/// a 2 byte length and may be split across, or share, segments (RFC 7766)
+#[derive(Debug, Default)]
+pub struct TcpReassembler {
+    streams: HashMap<FlowKey, Stream>,
+    last_eviction: Option<SystemTime>,
+}
+
+impl TcpReassembler {
+    /// Feed one TCP segment, returning the DNS messages it completed
+    pub fn process(
+        &mut self,
+        source: SocketAddr,
+        destination: SocketAddr,
+        header: &TcpHeader,
+        payload: &[u8],
+        timestamp: SystemTime,
+    ) -> Vec<Vec<u8>> {
+        self.evict_idle(timestamp);
+
+        let key = FlowKey {
+            source,
+            destination,
+        };
+
+        if header.rst {
+            self.streams.remove(&key);
+            return Vec::new();
+        }
+
+        if header.syn {
+            // The SYN consumes one sequence number
+            let stream = Stream::new(header.sequence.wrapping_add(1), timestamp);
+            self.streams.insert(key, stream);
+        }
+
+        let mut messages = Vec::new();
+        if !payload.is_empty() {
+            // If we missed the handshake, pick the stream up from this segment
+            let stream = self
+                .streams
+                .entry(key)
+                .or_insert_with(|| Stream::new(header.sequence, timestamp));
+            stream.last_seen = timestamp;
+
+            if stream.push(header.sequence, payload) {
+                messages = stream.drain_messages();
+            } else {
+                self.streams.remove(&key);
+            }
+        }
+
+        if header.fin {
+            self.streams.remove(&key);
+        }
+
+        messages
+    }
+
+    fn evict_idle(&mut self, now: SystemTime) {
+        let due = self
+            .last_eviction
+            .is_none_or(|last| now.duration_since(last).unwrap_or_default() >= STREAM_IDLE_TIMEOUT);
+        if !due {
+            return;
+        }
+
+        self.streams.retain(|_, stream| {
+            now.duration_since(stream.last_seen).unwrap_or_default() < STREAM_IDLE_TIMEOUT
+        });
+        self.last_eviction = Some(now);
+    }
+}
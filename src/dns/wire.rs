@@ -0,0 +1,159 @@
+use dns_parser::{Header, Name};
+
+use super::{
+    edns::Edns, record::RecordData, DnsHeader, DnsQuestion, DnsRecord, Message, QuestionType,
+};
+
+const HEADER_LEN: usize = 12;
+/// The OPT pseudo-record carrying EDNS(0) parameters (RFC 6891)
+const TYPE_OPT: u16 = 41;
+/// mDNS uses the top bit of the class as the QU bit in questions and the
+/// cache-flush bit in records (RFC 6762 §5.4, §10.2)
+const CLASS_MDNS_FLAG: u16 = 0x8000;
+
+/// Reads big-endian fields from a message, shared with the NBNS decoder
+pub(super) struct Reader<'a> {
+    pub(super) data: &'a [u8],
+    pub(super) pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(super) fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub(super) fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    pub(super) fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    pub(super) fn u32(&mut self) -> Option<u32> {
+        self.bytes(4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Read a possibly compressed domain name
+    fn domain_name(&mut self) -> Option<String> {
+        let name = Name::scan(self.data.get(self.pos..)?, self.data).ok()?;
+        self.pos += name.byte_len();
+        Some(name.to_string())
+    }
+}
+
+/// A resource record as it appears on the wire, before its data is decoded
+struct RawRecord<'a> {
+    name: String,
+    record_type: u16,
+    class: u16,
+    ttl: u32,
+    rdata: &'a [u8],
+}
+
+impl RawRecord<'_> {
+    fn decode(self, message: &[u8]) -> DnsRecord {
+        DnsRecord {
+            name: self.name,
+            // TTLs with the top bit set are treated as zero (RFC 2181 §8)
+            ttl: if self.ttl > i32::MAX as u32 {
+                0
+            } else {
+                self.ttl
+            },
+            data: RecordData::decode(self.record_type, self.rdata, message),
+            cache_flush: self.class & CLASS_MDNS_FLAG != 0,
+        }
+    }
+}
+
+fn parse_question(reader: &mut Reader) -> Option<DnsQuestion> {
+    let name = reader.domain_name()?;
+    let query_type = QuestionType::Dns(reader.u16()?);
+    let class = reader.u16()?;
+    Some(DnsQuestion {
+        name,
+        query_type,
+        unicast_response: class & CLASS_MDNS_FLAG != 0,
+    })
+}
+
+fn parse_record<'a>(reader: &mut Reader<'a>) -> Option<RawRecord<'a>> {
+    let name = reader.domain_name()?;
+    let record_type = reader.u16()?;
+    let class = reader.u16()?;
+    let ttl = reader.u32()?;
+    let len = reader.u16()? as usize;
+    let rdata = reader.bytes(len)?;
+    Some(RawRecord {
+        name,
+        record_type,
+        class,
+        ttl,
+        rdata,
+    })
+}
+
+/// Decode a message in the standard DNS format, shared by DNS, mDNS and LLMNR.
+/// dns_parser rejects a whole message over a single question or record of a
+/// type it doesn't know, such as HTTPS or RRSIG, so the sections are walked
+/// here and it's only used for the header, names and the RDATA it can decode.
+pub(super) fn parse(data: &[u8]) -> Option<Message> {
+    let dns = Header::parse(data).ok()?;
+    let mut reader = Reader::new(data);
+    reader.bytes(HEADER_LEN)?;
+
+    let header = DnsHeader {
+        id: dns.id,
+        is_response: !dns.query,
+        opcode: dns.opcode,
+        authoritative: dns.authoritative,
+        truncated: dns.truncated,
+        recursion_desired: dns.recursion_desired,
+        recursion_available: dns.recursion_available,
+        authenticated_data: dns.authenticated_data,
+        checking_disabled: dns.checking_disabled,
+        broadcast: false,
+        rcode: dns.response_code,
+    };
+
+    let questions = (0..dns.questions)
+        .map(|_| parse_question(&mut reader))
+        .collect::<Option<Vec<_>>>()?;
+    let mut records = |count: u16| {
+        (0..count)
+            .map(|_| parse_record(&mut reader))
+            .collect::<Option<Vec<_>>>()
+    };
+    let answers = records(dns.answers)?;
+    let authorities = records(dns.nameservers)?;
+    let additionals = records(dns.additional)?;
+
+    let mut edns = None;
+    let mut decoded_additionals = Vec::new();
+    for record in additionals {
+        if record.record_type == TYPE_OPT && edns.is_none() {
+            edns = Some(Edns::decode(record.class, record.ttl, record.rdata));
+        } else {
+            decoded_additionals.push(record.decode(data));
+        }
+    }
+
+    Some(Message {
+        header,
+        questions,
+        answers: answers
+            .into_iter()
+            .map(|record| record.decode(data))
+            .collect(),
+        authorities: authorities
+            .into_iter()
+            .map(|record| record.decode(data))
+            .collect(),
+        additionals: decoded_additionals,
+        edns,
+    })
+}
@@ -1,20 +1,39 @@
+mod attribution;
 mod capture;
 mod cli;
+mod display_filter;
 mod dns;
 mod net;
+mod privileges;
 mod shared;
+mod shutdown;
 mod tui;
 mod util;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use capture::dns_providers;
 use clap::Parser;
-use cli::Args;
+use cli::{Args, OutputFormat};
 use colored::*;
 use std::{process, sync::mpsc, thread};
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
+    // Queries are only checked against the host's own resolvers when watching
+    // all of its live traffic
+    let system_resolvers = if args.dns_providers.is_none() && args.read.is_none() {
+        dns_providers::detect_system_resolvers()
+    } else {
+        Vec::new()
+    };
+
+    // Providers are looked up by name during validation
+    if let Err(e) = dns_providers::load_catalog(args.providers_file.as_deref(), &system_resolvers) {
+        eprintln!("{}: {:#}", "Error".red().bold(), e);
+        process::exit(1);
+    }
+
     if let Err(e) = args.validate() {
         eprintln!("{}: {}", "Error".red().bold(), e);
         process::exit(1);
@@ -31,45 +50,94 @@ fn main() -> Result<()> {
         // TUI Mode
         println!("Starting DustCloud DNS Monitor in TUI mode...");
 
+        let captures = open_captures(&args);
+        shutdown::install_signal_handlers();
+
         // Spawn capture thread with tx sender
         let capture_args = args.clone();
         let capture_tx = tx.clone();
-        thread::spawn(move || {
-            if let Err(e) = capture::start_capture_with_channel(&capture_args, capture_tx) {
-                eprintln!("{}: {}", "Error during capture".red().bold(), e);
-                process::exit(1);
-            }
+        let capture = thread::spawn(move || {
+            capture::start_capture_with_channel(&capture_args, captures, capture_tx)
         });
 
-        tui::run_tui(rx)?;
+        let tui_result = tui::run_tui(rx, args.window, args.read.is_none());
+        // Errors are reported once the terminal has been restored
+        shutdown::request();
+        let capture_result = capture
+            .join()
+            .unwrap_or_else(|_| Err(anyhow!("The capture thread panicked")));
+        tui_result?;
+        finish_capture(capture_result)?;
     } else {
-        println!("{}", "DustCloud DNS Monitor".green().bold());
-        println!("Version: {}", env!("CARGO_PKG_VERSION"));
+        // Keep stdout machine-readable when emitting JSON
+        if args.output_format == OutputFormat::Text {
+            println!("{}", "DustCloud DNS Monitor".green().bold());
+            println!("Version: {}", env!("CARGO_PKG_VERSION"));
+        }
 
         if args.verbose {
             println!("\n{}", "Configuration:".yellow());
             if let Some(dns_providers) = &args.dns_providers {
                 println!("  Set providers DNS only: {:?}", dns_providers);
             }
-            if let Some(device) = &args.device {
-                println!("  Network device: {}", device);
-            } else {
+            if let Some(bpf) = &args.bpf {
+                println!("  Extra capture filter: {}", bpf);
+            }
+            if let Some(filter) = &args.filter {
+                println!("  Display filter: {}", filter);
+            }
+            if !system_resolvers.is_empty() {
+                println!("  System resolvers: {:?}", system_resolvers);
+            }
+            if let Some(path) = &args.read {
+                println!("  Capture file: {}", path.display());
+            } else if args.devices.is_empty() {
                 println!("  Network device: <auto-detect>");
+            } else {
+                println!("  Network devices: {}", args.devices.join(", "));
             }
-            println!("");
+            println!();
         }
 
-        #[cfg(unix)]
-        check_permissions();
+        let captures = open_captures(&args);
+        shutdown::install_signal_handlers();
 
         // Start packet capture (standard CLI mode)
-        if let Err(e) = capture::start_capture(&args) {
+        finish_capture(capture::start_capture(&args, captures))?;
+    }
+
+    Ok(())
+}
+
+/// Report how capturing ended: the error that stopped it, or a summary of
+/// what was seen. Goes to stderr so it doesn't mix with JSON output.
+fn finish_capture(result: Result<capture::CaptureSummary>) -> Result<()> {
+    match result {
+        Ok(summary) => {
+            eprintln!("\n{}", summary);
+            Ok(())
+        }
+        Err(e) => {
             eprintln!("{}: {}", "Error during capture".red().bold(), e);
-            return Err(e);
+            Err(e)
         }
     }
+}
 
-    Ok(())
+/// Open the captures while still privileged, then give up root and any
+/// capabilities before starting the threads that parse packets and draw the TUI
+fn open_captures(args: &Args) -> capture::Captures {
+    let captures = capture::open_captures(args).unwrap_or_else(|e| {
+        eprintln!("{}: {:#}", "Error".red().bold(), e);
+        process::exit(1);
+    });
+    // Only live traffic is traced back to the processes that sent it
+    let attributing = args.read.is_none();
+    if let Err(e) = privileges::drop_privileges(args.user.as_deref(), args.verbose, attributing) {
+        eprintln!("{}: {:#}", "Error dropping privileges".red().bold(), e);
+        process::exit(1);
+    }
+    captures
 }
 
 fn list_devices() -> Result<()> {
@@ -100,21 +168,7 @@ fn list_devices() -> Result<()> {
     }
 
     println!("To use a specific device, run with: --device DEVICE_NAME");
+    println!("Repeat --device to capture on several, or use --device all");
 
     Ok(())
 }
-
-#[cfg(unix)]
-fn check_permissions() {
-    let euid = unsafe { libc::geteuid() };
-    if euid != 0 {
-        eprintln!(
-            "{}",
-            "Warning: This program may require root privileges to capture packets."
-                .yellow()
-                .bold()
-        );
-        eprintln!("If capture fails, try running with sudo.");
-        eprintln!("");
-    }
-}
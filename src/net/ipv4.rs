@@ -0,0 +1,38 @@
+use std::net::Ipv4Addr;
+
+/// The fields of an IPv4 header that dustcloud cares about
+#[derive(Debug, Clone)]
+pub struct Ipv4Header {
+    pub protocol: u8,
+    pub source: Ipv4Addr,
+    pub destination: Ipv4Addr,
+}
+
+/// Decode an IPv4 header, returning it along with the bytes that follow it.
+/// Non-initial fragments are rejected since they carry no transport header.
+pub fn decode(data: &[u8]) -> Option<(Ipv4Header, &[u8])> {
+    if data.len() < 20 || data[0] >> 4 != 4 {
+        return None;
+    }
+
+    let header_len = (data[0] & 0x0f) as usize * 4;
+    let total_len = u16::from_be_bytes([data[2], data[3]]) as usize;
+    if header_len < 20 || data.len() < header_len || total_len < header_len {
+        return None;
+    }
+
+    let fragment_offset = u16::from_be_bytes([data[6], data[7]]) & 0x1fff;
+    if fragment_offset != 0 {
+        return None;
+    }
+
+    let header = Ipv4Header {
+        protocol: data[9],
+        source: Ipv4Addr::new(data[12], data[13], data[14], data[15]),
+        destination: Ipv4Addr::new(data[16], data[17], data[18], data[19]),
+    };
+
+    // Trim any link-layer padding or trailer, but tolerate snaplen truncation
+    let end = total_len.min(data.len());
+    Some((header, &data[header_len..end]))
+}
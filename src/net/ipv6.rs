@@ -0,0 +1,69 @@
+use std::net::Ipv6Addr;
+
+// Extension headers that share the generic (next header, length) layout
+const EXT_HOP_BY_HOP: u8 = 0;
+const EXT_ROUTING: u8 = 43;
+const EXT_DESTINATION_OPTIONS: u8 = 60;
+const EXT_MOBILITY: u8 = 135;
+const EXT_HIP: u8 = 139;
+const EXT_SHIM6: u8 = 140;
+// Extension headers with their own layout
+const EXT_FRAGMENT: u8 = 44;
+const EXT_AUTHENTICATION: u8 = 51;
+
+/// The fields of an IPv6 header that dustcloud cares about
+#[derive(Debug, Clone)]
+pub struct Ipv6Header {
+    pub source: Ipv6Addr,
+    pub destination: Ipv6Addr,
+    /// The upper-layer protocol found after walking any extension headers
+    pub protocol: u8,
+}
+
+/// Decode an IPv6 header and its extension header chain, returning it along
+/// with the upper-layer payload. Non-initial fragments are rejected since they
+/// carry no transport header.
+pub fn decode(data: &[u8]) -> Option<(Ipv6Header, &[u8])> {
+    if data.len() < 40 || data[0] >> 4 != 6 {
+        return None;
+    }
+
+    let payload_len = u16::from_be_bytes([data[4], data[5]]) as usize;
+    let source: [u8; 16] = data[8..24].try_into().ok()?;
+    let destination: [u8; 16] = data[24..40].try_into().ok()?;
+
+    // Trim any link-layer padding or trailer, but tolerate snaplen truncation
+    let end = (40 + payload_len).min(data.len());
+    let mut payload = &data[40..end];
+    let mut next_header = data[6];
+
+    loop {
+        let header_len = match next_header {
+            EXT_HOP_BY_HOP
+            | EXT_ROUTING
+            | EXT_DESTINATION_OPTIONS
+            | EXT_MOBILITY
+            | EXT_HIP
+            | EXT_SHIM6 => (*payload.get(1)? as usize + 1) * 8,
+            EXT_FRAGMENT => {
+                let offset = u16::from_be_bytes([*payload.get(2)?, *payload.get(3)?]) >> 3;
+                if offset != 0 {
+                    return None;
+                }
+                8
+            }
+            EXT_AUTHENTICATION => (*payload.get(1)? as usize + 2) * 4,
+            _ => break,
+        };
+
+        next_header = *payload.first()?;
+        payload = payload.get(header_len..)?;
+    }
+
+    let header = Ipv6Header {
+        source: Ipv6Addr::from(source),
+        destination: Ipv6Addr::from(destination),
+        protocol: next_header,
+    };
+    Some((header, payload))
+}
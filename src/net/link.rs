@@ -0,0 +1,301 @@
+use std::fmt;
+
+use pcap::Linktype;
+
+use super::{ETHERTYPE_IPV4, ETHERTYPE_IPV6};
+
+// VLAN tag protocol identifiers (802.1Q, 802.1ad and the legacy QinQ value)
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+const ETHERTYPE_QINQ_LEGACY: u16 = 0x9100;
+
+// DLT_RAW is 12 on most platforms but 14 on OpenBSD; savefiles use LINKTYPE_RAW (101)
+const DLT_RAW: i32 = 12;
+const DLT_RAW_OPENBSD: i32 = 14;
+const LINKTYPE_LINUX_SLL2: i32 = 276;
+
+/// Link-layer information decoded from the start of a captured frame
+#[derive(Debug, Clone)]
+pub enum LinkLayer {
+    Ethernet {
+        source: [u8; 6],
+        destination: [u8; 6],
+        /// 802.1Q/802.1ad VLAN IDs, outermost first
+        vlans: Vec<u16>,
+    },
+    /// BSD loopback encapsulation (DLT_NULL / DLT_LOOP)
+    Loopback { family: u32 },
+    /// Linux "any" device cooked capture (SLL / SLL2)
+    LinuxCooked {
+        packet_type: u16,
+        interface_index: Option<u32>,
+    },
+    /// Bare IP packets with no link-layer header
+    Raw,
+    Ieee80211 {
+        /// Whether the frame was wrapped in a radiotap header
+        radiotap: bool,
+    },
+}
+
+/// Strip the link-layer header for `linktype`, returning the decoded link layer,
+/// the EtherType of the encapsulated protocol and the remaining bytes.
+pub fn decode(linktype: Linktype, data: &[u8]) -> Option<(LinkLayer, u16, &[u8])> {
+    match linktype {
+        Linktype::ETHERNET => decode_ethernet(data),
+        Linktype::NULL => decode_loopback(data, false),
+        Linktype::LOOP => decode_loopback(data, true),
+        Linktype::RAW | Linktype::IPV4 | Linktype::IPV6 => decode_raw(data),
+        Linktype(DLT_RAW) | Linktype(DLT_RAW_OPENBSD) => decode_raw(data),
+        Linktype::LINUX_SLL => decode_linux_sll(data),
+        Linktype(LINKTYPE_LINUX_SLL2) => decode_linux_sll2(data),
+        Linktype::IEEE802_11 => decode_ieee80211(data, false),
+        Linktype::IEEE802_11_RADIOTAP => decode_radiotap(data),
+        _ => None,
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn decode_ethernet(data: &[u8]) -> Option<(LinkLayer, u16, &[u8])> {
+    if data.len() < 14 {
+        return None;
+    }
+
+    let mut destination = [0u8; 6];
+    let mut source = [0u8; 6];
+    destination.copy_from_slice(&data[0..6]);
+    source.copy_from_slice(&data[6..12]);
+
+    // Walk any stacked VLAN tags to find the real EtherType
+    let mut vlans = Vec::new();
+    let mut offset = 12;
+    let mut ethertype = read_u16(data, offset)?;
+    while matches!(
+        ethertype,
+        ETHERTYPE_VLAN | ETHERTYPE_QINQ | ETHERTYPE_QINQ_LEGACY
+    ) {
+        let tci = read_u16(data, offset + 2)?;
+        vlans.push(tci & 0x0fff);
+        offset += 4;
+        ethertype = read_u16(data, offset)?;
+    }
+
+    let link = LinkLayer::Ethernet {
+        source,
+        destination,
+        vlans,
+    };
+    Some((link, ethertype, &data[offset + 2..]))
+}
+
+fn decode_loopback(data: &[u8], network_order: bool) -> Option<(LinkLayer, u16, &[u8])> {
+    let bytes: [u8; 4] = data.get(0..4)?.try_into().ok()?;
+
+    // DLT_NULL stores the address family in the capturing host's byte order,
+    // which may not be ours when reading a savefile from another machine
+    let family = if network_order {
+        u32::from_be_bytes(bytes)
+    } else {
+        let family = u32::from_ne_bytes(bytes);
+        if family > 0xffff {
+            family.swap_bytes()
+        } else {
+            family
+        }
+    };
+
+    let ethertype = match family {
+        2 => ETHERTYPE_IPV4,
+        // AF_INET6 differs between BSDs, macOS and Linux
+        10 | 24 | 28 | 30 => ETHERTYPE_IPV6,
+        _ => return None,
+    };
+
+    Some((LinkLayer::Loopback { family }, ethertype, &data[4..]))
+}
+
+fn decode_raw(data: &[u8]) -> Option<(LinkLayer, u16, &[u8])> {
+    let ethertype = match data.first()? >> 4 {
+        4 => ETHERTYPE_IPV4,
+        6 => ETHERTYPE_IPV6,
+        _ => return None,
+    };
+    Some((LinkLayer::Raw, ethertype, data))
+}
+
+fn decode_linux_sll(data: &[u8]) -> Option<(LinkLayer, u16, &[u8])> {
+    if data.len() < 16 {
+        return None;
+    }
+    let link = LinkLayer::LinuxCooked {
+        packet_type: read_u16(data, 0)?,
+        interface_index: None,
+    };
+    Some((link, read_u16(data, 14)?, &data[16..]))
+}
+
+fn decode_linux_sll2(data: &[u8]) -> Option<(LinkLayer, u16, &[u8])> {
+    if data.len() < 20 {
+        return None;
+    }
+    let interface_index = u32::from_be_bytes(data[4..8].try_into().ok()?);
+    let link = LinkLayer::LinuxCooked {
+        packet_type: data[10] as u16,
+        interface_index: Some(interface_index),
+    };
+    Some((link, read_u16(data, 0)?, &data[20..]))
+}
+
+fn decode_radiotap(data: &[u8]) -> Option<(LinkLayer, u16, &[u8])> {
+    if data.len() < 8 {
+        return None;
+    }
+    let header_len = u16::from_le_bytes([data[2], data[3]]) as usize;
+    if data.len() < header_len {
+        return None;
+    }
+
+    let mut frame = &data[header_len..];
+    if radiotap_has_fcs(&data[..header_len]) {
+        frame = &frame[..frame.len().saturating_sub(4)];
+    }
+
+    let (_, ethertype, payload) = decode_ieee80211(frame, true)?;
+    Some((LinkLayer::Ieee80211 { radiotap: true }, ethertype, payload))
+}
+
+/// Check the radiotap Flags field for the "frame includes FCS" bit
+fn radiotap_has_fcs(header: &[u8]) -> bool {
+    const PRESENT_TSFT: u32 = 1 << 0;
+    const PRESENT_FLAGS: u32 = 1 << 1;
+    const PRESENT_EXT: u32 = 1 << 31;
+    const FLAG_FCS: u8 = 0x10;
+
+    let read_present = |offset: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(
+            header.get(offset..offset + 4)?.try_into().ok()?,
+        ))
+    };
+
+    let Some(present) = read_present(4) else {
+        return false;
+    };
+    if present & PRESENT_FLAGS == 0 {
+        return false;
+    }
+
+    // Skip any extended presence bitmaps
+    let mut offset = 8;
+    let mut word = present;
+    while word & PRESENT_EXT != 0 {
+        match read_present(offset) {
+            Some(next) => word = next,
+            None => return false,
+        }
+        offset += 4;
+    }
+
+    // TSFT is the only field before Flags and is 8-byte aligned
+    if present & PRESENT_TSFT != 0 {
+        offset = offset.next_multiple_of(8) + 8;
+    }
+
+    header
+        .get(offset)
+        .is_some_and(|flags| flags & FLAG_FCS != 0)
+}
+
+fn decode_ieee80211(data: &[u8], radiotap: bool) -> Option<(LinkLayer, u16, &[u8])> {
+    const TYPE_DATA: u8 = 2;
+    const FLAG_TO_DS: u8 = 0x01;
+    const FLAG_FROM_DS: u8 = 0x02;
+    const FLAG_PROTECTED: u8 = 0x40;
+    const FLAG_ORDER: u8 = 0x80;
+    const LLC_SNAP: [u8; 6] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00];
+
+    let frame_control = *data.first()?;
+    let flags = *data.get(1)?;
+    let frame_type = (frame_control >> 2) & 0x03;
+    let subtype = frame_control >> 4;
+
+    // Only unencrypted data frames carry IP packets we can read
+    if frame_type != TYPE_DATA || flags & FLAG_PROTECTED != 0 {
+        return None;
+    }
+    // Null-function subtypes carry no payload
+    if subtype & 0x04 != 0 {
+        return None;
+    }
+
+    let mut header_len = 24;
+    if flags & FLAG_TO_DS != 0 && flags & FLAG_FROM_DS != 0 {
+        header_len += 6; // Fourth address
+    }
+    let qos = subtype & 0x08 != 0;
+    if qos {
+        header_len += 2;
+        if flags & FLAG_ORDER != 0 {
+            header_len += 4; // HT control
+        }
+    }
+
+    let llc = data.get(header_len..header_len + 8)?;
+    if llc[..6] != LLC_SNAP {
+        return None;
+    }
+    let ethertype = u16::from_be_bytes([llc[6], llc[7]]);
+
+    Some((
+        LinkLayer::Ieee80211 { radiotap },
+        ethertype,
+        &data[header_len + 8..],
+    ))
+}
+
+fn format_mac(mac: &[u8; 6]) -> String {
+    mac.iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+impl fmt::Display for LinkLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkLayer::Ethernet {
+                source,
+                destination,
+                vlans,
+            } => {
+                write!(
+                    f,
+                    "Ethernet {} -> {}",
+                    format_mac(source),
+                    format_mac(destination)
+                )?;
+                for vlan in vlans {
+                    write!(f, " vlan {}", vlan)?;
+                }
+                Ok(())
+            }
+            LinkLayer::Loopback { family } => write!(f, "Loopback (family {})", family),
+            LinkLayer::LinuxCooked {
+                packet_type,
+                interface_index,
+            } => {
+                write!(f, "Linux cooked (packet type {}", packet_type)?;
+                if let Some(index) = interface_index {
+                    write!(f, ", ifindex {}", index)?;
+                }
+                write!(f, ")")
+            }
+            LinkLayer::Raw => write!(f, "Raw IP"),
+            LinkLayer::Ieee80211 { radiotap: true } => write!(f, "802.11 (radiotap)"),
+            LinkLayer::Ieee80211 { radiotap: false } => write!(f, "802.11"),
+        }
+    }
+}
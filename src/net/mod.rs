@@ -1,34 +1,115 @@
-// Function to extract IP addresses from a packet
-pub fn extract_ip_addresses(data: &[u8]) -> (String, String) {
-    // Ensure packet is large enough to contain Ethernet + IP headers
-    if data.len() < 34 {
-        // Minimum size for Ethernet (14) + IPv4 (20) headers
-        return ("unknown".to_string(), "unknown".to_string());
+use std::net::IpAddr;
+
+use pcap::Linktype;
+
+mod ipv4;
+mod ipv6;
+mod link;
+mod tcp;
+mod tls;
+mod udp;
+
+pub use ipv4::Ipv4Header;
+pub use ipv6::Ipv6Header;
+pub use link::LinkLayer;
+pub use tcp::TcpHeader;
+pub use tls::client_hello_server_name;
+pub use udp::{ChecksumStatus, UdpHeader};
+
+pub const ETHERTYPE_IPV4: u16 = 0x0800;
+pub const ETHERTYPE_IPV6: u16 = 0x86dd;
+
+pub const IPPROTO_TCP: u8 = 6;
+pub const IPPROTO_UDP: u8 = 17;
+
+/// Network-layer header of a decoded packet
+#[derive(Debug, Clone)]
+pub enum NetworkLayer {
+    Ipv4(Ipv4Header),
+    Ipv6(Ipv6Header),
+}
+
+impl NetworkLayer {
+    pub fn source(&self) -> IpAddr {
+        match self {
+            NetworkLayer::Ipv4(header) => IpAddr::V4(header.source),
+            NetworkLayer::Ipv6(header) => IpAddr::V6(header.source),
+        }
     }
 
-    // Skip Ethernet header (typically 14 bytes)
-    let ethernet_header_size = 14;
+    pub fn destination(&self) -> IpAddr {
+        match self {
+            NetworkLayer::Ipv4(header) => IpAddr::V4(header.destination),
+            NetworkLayer::Ipv6(header) => IpAddr::V6(header.destination),
+        }
+    }
 
-    // Check if this is an IPv4 packet (EtherType 0x0800)
-    let ethertype = ((data[12] as u16) << 8) | (data[13] as u16);
-    if ethertype != 0x0800 {
-        return ("unknown".to_string(), "unknown".to_string());
+    pub fn protocol(&self) -> u8 {
+        match self {
+            NetworkLayer::Ipv4(header) => header.protocol,
+            NetworkLayer::Ipv6(header) => header.protocol,
+        }
     }
+}
+
+/// Transport-layer header of a decoded packet
+#[derive(Debug, Clone)]
+pub enum TransportLayer {
+    Tcp(TcpHeader),
+    Udp(UdpHeader),
+    /// A protocol we don't decode; the payload starts right after the IP header
+    Other {
+        #[allow(unused)]
+        protocol: u8,
+    },
+}
+
+/// A captured frame split into its link, network and transport layers
+#[derive(Debug)]
+pub struct DecodedPacket<'a> {
+    pub link: LinkLayer,
+    pub network: NetworkLayer,
+    pub transport: TransportLayer,
+    pub payload: &'a [u8],
+}
+
+/// The network-layer bytes of a frame, i.e. everything after the link header
+pub fn strip_link_layer(linktype: Linktype, data: &[u8]) -> Option<&[u8]> {
+    link::decode(linktype, data).map(|(_, _, network)| network)
+}
 
-    // Get IP header fields
-    let ip_header = &data[ethernet_header_size..];
+/// Decode a raw frame captured on a link of type `linktype`
+pub fn decode_packet(linktype: Linktype, data: &[u8]) -> Option<DecodedPacket<'_>> {
+    let (link, ethertype, data) = link::decode(linktype, data)?;
 
-    // Extract source IP address (bytes 12-15 of IP header)
-    let src_ip = format!(
-        "{}.{}.{}.{}",
-        ip_header[12], ip_header[13], ip_header[14], ip_header[15]
-    );
+    let (network, data) = match ethertype {
+        ETHERTYPE_IPV4 => {
+            let (header, rest) = ipv4::decode(data)?;
+            (NetworkLayer::Ipv4(header), rest)
+        }
+        ETHERTYPE_IPV6 => {
+            let (header, rest) = ipv6::decode(data)?;
+            (NetworkLayer::Ipv6(header), rest)
+        }
+        _ => return None,
+    };
 
-    // Extract destination IP address (bytes 16-19 of IP header)
-    let dst_ip = format!(
-        "{}.{}.{}.{}",
-        ip_header[16], ip_header[17], ip_header[18], ip_header[19]
-    );
+    let (transport, payload) = match network.protocol() {
+        IPPROTO_TCP => {
+            let (header, payload) = tcp::decode(data)?;
+            (TransportLayer::Tcp(header), payload)
+        }
+        IPPROTO_UDP => {
+            let (header, payload) = udp::decode(data, network.source(), network.destination())?;
+            (TransportLayer::Udp(header), payload)
+        }
+        protocol => (TransportLayer::Other { protocol }, data),
+    };
 
-    (src_ip, dst_ip)
+    Some(DecodedPacket {
+        link,
+        network,
+        transport,
+        payload,
+    })
 }
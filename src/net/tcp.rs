@@ -0,0 +1,33 @@
+/// The fields of a TCP header needed to follow a stream
+#[derive(Debug, Clone)]
+pub struct TcpHeader {
+    pub source_port: u16,
+    pub destination_port: u16,
+    pub sequence: u32,
+    pub syn: bool,
+    pub fin: bool,
+    pub rst: bool,
+}
+
+/// Decode a TCP header, returning it along with the segment payload
+pub fn decode(data: &[u8]) -> Option<(TcpHeader, &[u8])> {
+    if data.len() < 20 {
+        return None;
+    }
+
+    let header_len = (data[12] >> 4) as usize * 4;
+    if header_len < 20 || data.len() < header_len {
+        return None;
+    }
+
+    let flags = data[13];
+    let header = TcpHeader {
+        source_port: u16::from_be_bytes([data[0], data[1]]),
+        destination_port: u16::from_be_bytes([data[2], data[3]]),
+        sequence: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+        fin: flags & 0x01 != 0,
+        syn: flags & 0x02 != 0,
+        rst: flags & 0x04 != 0,
+    };
+    Some((header, &data[header_len..]))
+}
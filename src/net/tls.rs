@@ -0,0 +1,95 @@
+const CONTENT_TYPE_HANDSHAKE: u8 = 22;
+const HANDSHAKE_CLIENT_HELLO: u8 = 1;
+const EXTENSION_SERVER_NAME: u16 = 0;
+const NAME_TYPE_HOST_NAME: u8 = 0;
+
+/// Bounds-checked reader over a byte slice
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(..len)?;
+        self.data = &self.data[len..];
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    /// Skip a field preceded by a one byte length
+    fn skip_u8_prefixed(&mut self) -> Option<()> {
+        let len = self.u8()? as usize;
+        self.bytes(len).map(|_| ())
+    }
+
+    /// Skip a field preceded by a two byte length
+    fn skip_u16_prefixed(&mut self) -> Option<()> {
+        let len = self.u16()? as usize;
+        self.bytes(len).map(|_| ())
+    }
+}
+
+/// The server name (SNI) a TLS ClientHello at the start of `payload` asks for,
+/// lowercased. Only the first record is examined, so a ClientHello split across
+/// TCP segments is only recognised if the SNI falls in the first one.
+pub fn client_hello_server_name(payload: &[u8]) -> Option<String> {
+    let mut record = Reader::new(payload);
+    if record.u8()? != CONTENT_TYPE_HANDSHAKE {
+        return None;
+    }
+    record.bytes(2)?; // Record version
+    let len = record.u16()? as usize;
+    let mut handshake = Reader::new(record.bytes(len.min(record.remaining()))?);
+
+    if handshake.u8()? != HANDSHAKE_CLIENT_HELLO {
+        return None;
+    }
+    handshake.bytes(3)?; // Handshake length
+    handshake.bytes(2 + 32)?; // Client version and random
+    handshake.skip_u8_prefixed()?; // Session ID
+    handshake.skip_u16_prefixed()?; // Cipher suites
+    handshake.skip_u8_prefixed()?; // Compression methods
+
+    let len = handshake.u16()? as usize;
+    let mut extensions = Reader::new(handshake.bytes(len.min(handshake.remaining()))?);
+    while extensions.remaining() >= 4 {
+        let extension_type = extensions.u16()?;
+        let len = extensions.u16()? as usize;
+        let data = extensions.bytes(len)?;
+        if extension_type != EXTENSION_SERVER_NAME {
+            continue;
+        }
+
+        let mut server_names = Reader::new(data);
+        let len = server_names.u16()? as usize;
+        let mut list = Reader::new(server_names.bytes(len)?);
+        while list.remaining() >= 3 {
+            let name_type = list.u8()?;
+            let len = list.u16()? as usize;
+            let name = list.bytes(len)?;
+            if name_type == NAME_TYPE_HOST_NAME {
+                return std::str::from_utf8(name)
+                    .ok()
+                    .map(|name| name.to_ascii_lowercase());
+            }
+        }
+        return None;
+    }
+
+    None
+}
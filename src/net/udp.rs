@@ -0,0 +1,92 @@
+use std::net::IpAddr;
+
+use super::IPPROTO_UDP;
+
+/// Outcome of checking a UDP checksum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumStatus {
+    /// The sender left the checksum at zero (only allowed over IPv4)
+    Absent,
+    Valid,
+    /// Note that packets sent by the capturing host are often seen before the
+    /// NIC fills in the checksum, so these are reported rather than dropped
+    Invalid,
+    /// The datagram was truncated by the snapshot length so can't be checked
+    Unverified,
+}
+
+#[derive(Debug, Clone)]
+pub struct UdpHeader {
+    pub source_port: u16,
+    pub destination_port: u16,
+    pub checksum: ChecksumStatus,
+}
+
+/// Decode a UDP header carried between `source` and `destination`, returning it
+/// along with the datagram payload
+pub fn decode(data: &[u8], source: IpAddr, destination: IpAddr) -> Option<(UdpHeader, &[u8])> {
+    if data.len() < 8 {
+        return None;
+    }
+
+    let length = u16::from_be_bytes([data[4], data[5]]) as usize;
+    if length < 8 {
+        return None;
+    }
+
+    let checksum = u16::from_be_bytes([data[6], data[7]]);
+    let checksum = if checksum == 0 && source.is_ipv4() {
+        ChecksumStatus::Absent
+    } else if data.len() < length {
+        ChecksumStatus::Unverified
+    } else if verify_checksum(&data[..length], source, destination) {
+        ChecksumStatus::Valid
+    } else {
+        ChecksumStatus::Invalid
+    };
+
+    let header = UdpHeader {
+        source_port: u16::from_be_bytes([data[0], data[1]]),
+        destination_port: u16::from_be_bytes([data[2], data[3]]),
+        checksum,
+    };
+    let end = length.min(data.len());
+    Some((header, &data[8..end]))
+}
+
+/// Verify the ones' complement checksum over the pseudo-header and datagram
+fn verify_checksum(datagram: &[u8], source: IpAddr, destination: IpAddr) -> bool {
+    let mut sum: u32 = 0;
+    let mut add_bytes = |bytes: &[u8]| {
+        for chunk in bytes.chunks(2) {
+            let word = match chunk {
+                [hi, lo] => u16::from_be_bytes([*hi, *lo]),
+                [hi] => u16::from_be_bytes([*hi, 0]),
+                _ => 0,
+            };
+            sum += word as u32;
+        }
+    };
+
+    match (source, destination) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            add_bytes(&src.octets());
+            add_bytes(&dst.octets());
+            add_bytes(&[0, IPPROTO_UDP]);
+            add_bytes(&(datagram.len() as u16).to_be_bytes());
+        }
+        (IpAddr::V6(src), IpAddr::V6(dst)) => {
+            add_bytes(&src.octets());
+            add_bytes(&dst.octets());
+            add_bytes(&(datagram.len() as u32).to_be_bytes());
+            add_bytes(&[0, 0, 0, IPPROTO_UDP]);
+        }
+        _ => return false,
+    }
+    add_bytes(datagram);
+
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum == 0xffff
+}
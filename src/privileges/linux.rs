@@ -0,0 +1,187 @@
+use std::{ffi::CString, fs, io, os::unix::ffi::OsStrExt, path::Path};
+
+use anyhow::{anyhow, Result};
+
+const CAP_NET_ADMIN: u32 = 12;
+const CAP_NET_RAW: u32 = 13;
+/// What libpcap needs to open a live capture
+const CAPTURE_CAPABILITIES: [(u32, &str); 2] = [
+    (CAP_NET_RAW, "cap_net_raw"),
+    (CAP_NET_ADMIN, "cap_net_admin"),
+];
+
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+/// Extended attribute holding a file's capabilities (see capabilities(7))
+const FILE_CAPABILITY_XATTR: &str = "security.capability";
+const VFS_CAP_REVISION_MASK: u32 = 0xff00_0000;
+const VFS_CAP_REVISION_1: u32 = 0x0100_0000;
+const VFS_CAP_FLAGS_EFFECTIVE: u32 = 0x0000_0001;
+
+#[repr(C)]
+struct CapHeader {
+    version: u32,
+    pid: libc::c_int,
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct CapData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+/// Capabilities set on an executable with setcap
+pub struct FileCapabilities {
+    permitted: u64,
+    /// Whether they're raised on exec, i.e. set with `=ep` rather than `=p`
+    effective: bool,
+}
+
+/// The calling thread's effective capabilities, as listed in /proc/self/status
+pub fn effective_capabilities() -> u64 {
+    fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            let line = status.lines().find(|line| line.starts_with("CapEff:"))?;
+            u64::from_str_radix(line["CapEff:".len()..].trim(), 16).ok()
+        })
+        .unwrap_or(0)
+}
+
+/// The capabilities the executable at `path` was given with setcap, if any
+pub fn file_capabilities(path: &Path) -> Option<FileCapabilities> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let name = CString::new(FILE_CAPABILITY_XATTR).ok()?;
+    let mut buf = [0u8; 24];
+    let len = unsafe {
+        libc::getxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            buf.as_mut_ptr() as *mut libc::c_void,
+            buf.len(),
+        )
+    };
+    if len < 12 {
+        return None;
+    }
+
+    let word = |i: usize| u32::from_le_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]);
+    let magic = word(0);
+    // Revision 1 only has the low 32 capabilities; later ones have 64
+    let high = if magic & VFS_CAP_REVISION_MASK != VFS_CAP_REVISION_1 && len >= 20 {
+        word(12)
+    } else {
+        0
+    };
+    Some(FileCapabilities {
+        permitted: (high as u64) << 32 | word(4) as u64,
+        effective: magic & VFS_CAP_FLAGS_EFFECTIVE != 0,
+    })
+}
+
+fn has_capture_capabilities(capabilities: u64) -> bool {
+    CAPTURE_CAPABILITIES
+        .iter()
+        .all(|(cap, _)| capabilities & (1 << cap) != 0)
+}
+
+/// The capture capabilities this process has, e.g. "cap_net_raw, cap_net_admin"
+pub fn describe_capabilities() -> Option<String> {
+    let effective = effective_capabilities();
+    let names: Vec<&str> = CAPTURE_CAPABILITIES
+        .iter()
+        .filter(|(cap, _)| effective & (1 << cap) != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    (!names.is_empty()).then(|| names.join(", "))
+}
+
+/// Give up every capability, and the means to gain any back through exec.
+/// Capabilities belong to a thread, so this must run before any are spawned.
+pub fn clear_capabilities() -> Result<()> {
+    unsafe {
+        // Not supported before Linux 4.3, where there's nothing to clear
+        libc::prctl(
+            libc::PR_CAP_AMBIENT,
+            libc::PR_CAP_AMBIENT_CLEAR_ALL,
+            0,
+            0,
+            0,
+        );
+        if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+            return Err(anyhow!(
+                "Failed to set no_new_privs: {}",
+                io::Error::last_os_error()
+            ));
+        }
+
+        let header = CapHeader {
+            version: LINUX_CAPABILITY_VERSION_3,
+            pid: 0,
+        };
+        let data = [CapData::default(); 2];
+        if libc::syscall(libc::SYS_capset, &header, data.as_ptr()) != 0 {
+            return Err(anyhow!(
+                "Failed to clear capabilities: {}",
+                io::Error::last_os_error()
+            ));
+        }
+    }
+
+    if effective_capabilities() != 0 {
+        return Err(anyhow!(
+            "Capabilities are still in effect after clearing them"
+        ));
+    }
+    Ok(())
+}
+
+/// Explain why a capture couldn't be opened without root, and how to fix it
+pub fn permission_help() -> String {
+    let exe = std::env::current_exe().unwrap_or_else(|_| "dustcloud".into());
+    let uid = unsafe { libc::getuid() };
+    let setcap = format!(
+        "    sudo setcap cap_net_raw,cap_net_admin=eip {}",
+        exe.display()
+    );
+
+    let mut help = vec![
+        "Capturing packets needs root, or the cap_net_raw and cap_net_admin capabilities."
+            .to_string(),
+    ];
+    if uid == 0 {
+        help.push(format!(
+            "Running as root with {}, but capturing was still refused. A container may have \
+             been started without them, or a security module such as SELinux or AppArmor may \
+             be denying it.",
+            describe_capabilities().unwrap_or_else(|| "neither".to_string())
+        ));
+        return help.join("\n");
+    }
+    match file_capabilities(&exe) {
+        Some(file) if has_capture_capabilities(file.permitted) && !file.effective => {
+            help.push(
+                "The binary has both capabilities, but without the effective flag. Set them with:"
+                    .to_string(),
+            );
+            help.push(setcap);
+        }
+        Some(file) if has_capture_capabilities(file.permitted) => help.push(
+            "The binary has both capabilities, but they aren't in effect. Its file system may \
+             be mounted nosuid, or it was started in a way that doesn't grant them, e.g. \
+             under a debugger or in a container without them."
+                .to_string(),
+        ),
+        _ => {
+            help.push(format!(
+                "Running as uid {} with {}.",
+                uid,
+                describe_capabilities().unwrap_or_else(|| "neither".to_string())
+            ));
+            help.push("Run it with sudo, or give the binary the capabilities once:".to_string());
+            help.push(setcap);
+        }
+    }
+    help.join("\n")
+}
@@ -0,0 +1,119 @@
+#[cfg(unix)]
+use std::{env, ffi::CString, io};
+
+use anyhow::Result;
+#[cfg(unix)]
+use anyhow::{anyhow, Context};
+#[cfg(target_os = "linux")]
+use colored::Colorize;
+
+#[cfg(target_os = "linux")]
+mod linux;
+
+/// User to switch to when started as root other than through sudo
+#[cfg(unix)]
+const FALLBACK_USER: &str = "nobody";
+
+/// Give up root and every capability once the captures are open, so that
+/// parsing untrusted packets and drawing the TUI run unprivileged. Root
+/// switches to `user`, or else to the user who ran sudo, or else to nobody.
+/// `attributing` is whether queries will be traced back to processes, which
+/// nobody can't do. Must be called before any threads are started.
+#[cfg(unix)]
+pub fn drop_privileges(user: Option<&str>, verbose: bool, attributing: bool) -> Result<()> {
+    if unsafe { libc::geteuid() } == 0 {
+        let user = user
+            .map(str::to_string)
+            .or_else(|| env::var("SUDO_USER").ok().filter(|user| user != "root"))
+            .unwrap_or_else(|| FALLBACK_USER.to_string());
+        switch_user(&user)?;
+        if verbose {
+            println!("Dropped privileges to user {}", user);
+        }
+        // nobody owns no processes, and can't read any other user's /proc/*/fd
+        #[cfg(target_os = "linux")]
+        if attributing && user == FALLBACK_USER {
+            eprintln!(
+                "{}: running as {}, so queries can't be traced back to processes; \
+                 start dustcloud with sudo or --user to attribute that user's queries",
+                "Warning".yellow().bold(),
+                FALLBACK_USER
+            );
+        }
+    } else if let Some(user) = user {
+        return Err(anyhow!(
+            "--user {} needs dustcloud to be started as root",
+            user
+        ));
+    } else if verbose {
+        #[cfg(target_os = "linux")]
+        if let Some(capabilities) = linux::describe_capabilities() {
+            println!("Captures opened with capabilities: {}", capabilities);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    linux::clear_capabilities()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn drop_privileges(_user: Option<&str>, _verbose: bool, _attributing: bool) -> Result<()> {
+    Ok(())
+}
+
+/// Become `name`, with its group and supplementary groups
+#[cfg(unix)]
+fn switch_user(name: &str) -> Result<()> {
+    let c_name = CString::new(name).context("Invalid user name")?;
+    let passwd = unsafe { libc::getpwnam(c_name.as_ptr()) };
+    if passwd.is_null() {
+        return Err(anyhow!("Unknown user '{}'", name));
+    }
+    let (uid, gid) = unsafe { ((*passwd).pw_uid, (*passwd).pw_gid) };
+
+    // Groups first, as changing them needs root
+    if unsafe { libc::initgroups(c_name.as_ptr(), gid as _) } != 0 {
+        return Err(io::Error::last_os_error())
+            .with_context(|| format!("Failed to set the groups of user '{}'", name));
+    }
+    if unsafe { libc::setgid(gid) } != 0 {
+        return Err(io::Error::last_os_error())
+            .with_context(|| format!("Failed to switch to group {}", gid));
+    }
+    if unsafe { libc::setuid(uid) } != 0 {
+        return Err(io::Error::last_os_error())
+            .with_context(|| format!("Failed to switch to user '{}'", name));
+    }
+
+    if uid != 0 && unsafe { libc::setuid(0) } == 0 {
+        return Err(anyhow!(
+            "Root could be regained after switching to '{}'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Whether a capture failed to open for lack of privileges
+pub fn is_permission_error(error: &pcap::Error) -> bool {
+    let message = error.to_string().to_lowercase();
+    message.contains("permission") || message.contains("not permitted")
+}
+
+/// Explain how to get the privileges capturing needs on this platform
+pub fn permission_help() -> String {
+    #[cfg(target_os = "linux")]
+    return linux::permission_help();
+
+    #[cfg(target_os = "macos")]
+    return "Capturing packets needs read access to the /dev/bpf* devices. Run dustcloud \
+            with sudo, or install Wireshark's ChmodBPF helper, which gives the access_bpf \
+            group that access."
+        .to_string();
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    return "Capturing packets needs elevated privileges; try running dustcloud as an \
+            administrator."
+        .to_string();
+}
@@ -1,6 +1,10 @@
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
-use crate::capture::dns_providers::DnsProvider;
+use crate::{
+    attribution::ProcessInfo,
+    capture::dns_providers::DnsProvider,
+    dns::{DnsPacket, DnsProtocol, Edns, SessionEvent},
+};
 
 /// Types for sharing between tx/rx channels
 #[derive(Clone, Debug)]
@@ -8,9 +12,69 @@ pub enum TxEvent {
     DnsQuery {
         domain: String,
         query_type: String,
+        protocol: DnsProtocol,
         provider: DnsProvider,
         source: String,
         destination: String,
+        /// Sent to a resolver other than the system's configured ones
+        bypass: bool,
+        /// Interface the query was captured on, None for savefiles
+        interface: Option<String>,
+        /// Local process that sent the query, when it could be found
+        process: Option<ProcessInfo>,
+        /// EDNS(0) parameters sent with the query, e.g. a client subnet
+        edns: Option<Edns>,
         timestamp: SystemTime,
+        /// The whole query message, for the TUI's detail pane
+        packet: Box<DnsPacket>,
+    },
+    /// A query matched with its response
+    DnsTransaction {
+        domain: String,
+        query_type: String,
+        protocol: DnsProtocol,
+        provider: DnsProvider,
+        source: String,
+        destination: String,
+        /// Response code mnemonic, e.g. NOERROR or NXDOMAIN
+        rcode: String,
+        rtt: Duration,
+        /// EDNS(0) parameters of the response, e.g. extended DNS errors
+        edns: Option<Edns>,
+        /// When the query was sent
+        timestamp: SystemTime,
+        /// The whole response message
+        response: Box<DnsPacket>,
+    },
+    /// A link-local response that answers no captured query, e.g. an mDNS
+    /// announcement or an answer to a query sent before capturing began
+    DnsResponse {
+        domain: String,
+        query_type: String,
+        protocol: DnsProtocol,
+        source: String,
+        destination: String,
+        /// Interface the response was captured on, None for savefiles
+        interface: Option<String>,
+        timestamp: SystemTime,
+        /// The whole response message
+        response: Box<DnsPacket>,
+    },
+    /// A query that never received a response
+    DnsTimeout {
+        domain: String,
+        query_type: String,
+        protocol: DnsProtocol,
+        provider: DnsProvider,
+        source: String,
+        destination: String,
+        timestamp: SystemTime,
+    },
+    /// Activity on a DoT, DoH or DoQ session
+    EncryptedDnsSession {
+        provider: DnsProvider,
+        /// DoT, DoH or DoQ
+        protocol: String,
+        event: SessionEvent,
     },
 }
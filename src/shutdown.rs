@@ -0,0 +1,37 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set once dustcloud should stop: on SIGINT, SIGTERM or SIGHUP, when the TUI
+/// is closed, or when capturing fails. Checked by the capture threads, which
+/// stop reading so that the processing loop can drain what they've read.
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Ask every part of dustcloud to wind down
+pub fn request() {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Request a shutdown on SIGINT, SIGTERM and SIGHUP. A second signal exits
+/// straight away, in case shutting down gets stuck.
+#[cfg(unix)]
+pub fn install_signal_handlers() {
+    for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+        unsafe {
+            libc::signal(signal, handle_signal as *const () as libc::sighandler_t);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install_signal_handlers() {}
+
+#[cfg(unix)]
+extern "C" fn handle_signal(signal: libc::c_int) {
+    // Only async-signal-safe calls are allowed here
+    if REQUESTED.swap(true, Ordering::SeqCst) {
+        unsafe { libc::_exit(128 + signal) };
+    }
+}
@@ -0,0 +1,222 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    symbols,
+    text::Span,
+    widgets::{Axis, Block, Borders, Chart, Dataset, GraphType},
+    Frame,
+};
+use std::collections::VecDeque;
+
+use super::{format_span, pause::Pause, DnsTrafficData};
+use crate::capture::dns_providers::DnsProvider;
+
+// Buckets the chart window is divided into
+const BUCKETS: f64 = 60.0;
+
+// Queries counted in fixed-width time buckets, oldest first. Buckets that
+// saw no queries aren't stored.
+pub struct RateHistory {
+    // Seconds each bucket covers
+    width: f64,
+    // Bucket number, counting from the first packet seen, and its queries
+    buckets: VecDeque<(u64, u32)>,
+}
+
+impl RateHistory {
+    // History for a chart spanning `window` seconds. Buckets are at least a
+    // second wide, so short windows still count a useful number of queries
+    // in each.
+    pub fn new(window: f64) -> Self {
+        Self {
+            width: (window / BUCKETS).max(1.0),
+            buckets: VecDeque::new(),
+        }
+    }
+
+    // Count a query captured `time` seconds after the first packet seen.
+    // Queries mostly arrive in capture order, but those from another
+    // interface can be a little behind, landing in an earlier bucket.
+    pub fn record(&mut self, time: f64) {
+        let bucket = (time / self.width) as u64;
+        match self.buckets.back_mut() {
+            Some((last, queries)) if *last == bucket => *queries += 1,
+            Some((last, _)) if *last > bucket => {
+                let index = self.buckets.partition_point(|(stored, _)| *stored < bucket);
+                match self.buckets.get_mut(index) {
+                    Some((stored, queries)) if *stored == bucket => *queries += 1,
+                    _ => self.buckets.insert(index, (bucket, 1)),
+                }
+            }
+            _ => self.buckets.push_back((bucket, 1)),
+        }
+    }
+
+    // Drop buckets that ended before `cutoff`
+    pub fn prune(&mut self, cutoff: f64) {
+        while let Some((bucket, _)) = self.buckets.front()
+            && self.end(*bucket) < cutoff
+        {
+            self.buckets.pop_front();
+        }
+    }
+
+    fn end(&self, bucket: u64) -> f64 {
+        (bucket + 1) as f64 * self.width
+    }
+
+    // Queries per second in each bucket that ended between `start` and `end`,
+    // plotted at the time it ended. Buckets still filling aren't included, as
+    // their rate would read low.
+    fn rates(&self, start: f64, end: f64) -> Vec<(f64, f64)> {
+        let first = ((start / self.width).ceil() - 1.0).max(0.0) as u64;
+        let last = (end / self.width).floor() - 1.0;
+        if last < first as f64 {
+            return Vec::new();
+        }
+
+        let mut stored = self
+            .buckets
+            .range(self.buckets.partition_point(|(bucket, _)| *bucket < first)..)
+            .peekable();
+        (first..=last as u64)
+            .map(|bucket| {
+                let queries = match stored.peek() {
+                    Some((stored_bucket, queries)) if *stored_bucket == bucket => {
+                        stored.next();
+                        *queries
+                    }
+                    _ => 0,
+                };
+                (self.end(bucket), queries as f64 / self.width)
+            })
+            .collect()
+    }
+}
+
+// Each provider's query rate over the chart window ending at `now`, leaving
+// out providers with no queries in it
+fn provider_rates(data: &DnsTrafficData, now: f64) -> Vec<(DnsProvider, Vec<(f64, f64)>)> {
+    // Catalog order, with unrecognised resolvers last
+    DnsProvider::all()
+        .chain([DnsProvider::UNKNOWN])
+        .filter_map(|provider| {
+            let rates = data
+                .provider_history
+                .get(&provider)?
+                .rates(now - data.window_size, now);
+            rates
+                .iter()
+                .any(|(_, rate)| *rate > 0.0)
+                .then_some((provider, rates))
+        })
+        .collect()
+}
+
+// A round step splitting 0..`max` into about four ticks: 1, 2 or 5 times a
+// power of ten
+fn tick_step(max: f64) -> f64 {
+    let target = max / 4.0;
+    let magnitude = 10f64.powf(target.log10().floor());
+    [1.0, 2.0, 5.0]
+        .into_iter()
+        .map(|multiple| multiple * magnitude)
+        .find(|step| *step >= target)
+        .unwrap_or(10.0 * magnitude)
+}
+
+// A rate for the y-axis, without trailing zeros, e.g. "0.25", "2"
+fn format_rate(rate: f64) -> String {
+    let text = format!("{:.2}", rate);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+// How long before the present a point on the x-axis is, e.g. "-2m30s"
+fn format_ago(seconds: f64) -> String {
+    if seconds.round() == 0.0 {
+        "now".to_string()
+    } else {
+        format!("-{}", format_span(seconds))
+    }
+}
+
+// The traffic chart: queries per second to each provider over the window
+// leading up to the moment shown
+pub fn render(f: &mut Frame, area: Rect, data: &DnsTrafficData) {
+    let now = data.now();
+    let rates = provider_rates(data, now);
+
+    let datasets: Vec<Dataset> = rates
+        .iter()
+        .map(|(provider, rates)| {
+            Dataset::default()
+                .name(provider.as_str())
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(provider.color()))
+                .data(rates)
+        })
+        .collect();
+
+    // Scale the y-axis to the busiest bucket, rounded up to a tick
+    let peak = rates
+        .iter()
+        .flat_map(|(_, rates)| rates.iter().map(|(_, rate)| *rate))
+        .fold(1.0, f64::max);
+    let step = tick_step(peak);
+    let ticks = (peak / step).ceil() as u32;
+    let y_labels: Vec<Span> = (0..=ticks)
+        .map(|tick| {
+            Span::styled(
+                format_rate(tick as f64 * step),
+                Style::default().fg(Color::White),
+            )
+        })
+        .collect();
+
+    // Label the x-axis relative to the pause while scrubbed back
+    let offset = data.pause.as_ref().map_or(0.0, Pause::offset);
+    let title = if offset > 0.0 {
+        format!(
+            "DNS Traffic by Provider ({} before pausing)",
+            format_span(offset)
+        )
+    } else if data.pause.is_some() {
+        "DNS Traffic by Provider (paused)".to_string()
+    } else {
+        "DNS Traffic by Provider".to_string()
+    };
+    let x_labels: Vec<Span> = [data.window_size, data.window_size / 2.0, 0.0]
+        .into_iter()
+        .map(|ago| Span::styled(format_ago(ago + offset), Style::default().fg(Color::White)))
+        .collect();
+
+    let chart = Chart::new(datasets)
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    title,
+                    Style::default()
+                        .fg(Color::Blue)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Gray)),
+        )
+        .x_axis(
+            Axis::default()
+                .title(Span::styled("Time", Style::default().fg(Color::White)))
+                .style(Style::default().fg(Color::White))
+                .bounds([now - data.window_size, now])
+                .labels(x_labels),
+        )
+        .y_axis(
+            Axis::default()
+                .title(Span::styled("Queries/s", Style::default().fg(Color::White)))
+                .style(Style::default().fg(Color::White))
+                .bounds([0.0, ticks as f64 * step])
+                .labels(y_labels),
+        );
+
+    f.render_widget(chart, area);
+}
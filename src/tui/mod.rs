@@ -1,384 +1,1197 @@
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    cursor::Show,
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    symbols,
+    text::Line,
     text::Span,
-    widgets::{Axis, Block, Borders, Chart, Dataset, List, ListItem},
-    Terminal,
+    widgets::{Block, Borders, List, ListItem, Paragraph, Tabs},
+    Frame, Terminal,
 };
 use std::{
     collections::{HashMap, VecDeque},
-    io,
+    hash::Hash,
+    io, panic,
     sync::mpsc::Receiver,
-    time::{Duration, Instant, UNIX_EPOCH},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use crate::{capture::dns_providers::DnsProvider, shared::TxEvent};
+use crate::{
+    attribution::ProcessInfo,
+    capture::dns_providers::{system_resolvers, DnsProvider},
+    dns::{DnsPacket, DnsProtocol, SessionEvent},
+    shared::TxEvent,
+    shutdown,
+};
+use chart::RateHistory;
+use pause::Pause;
+use query_log::QueryLog;
+use search::{is_noisy, Search};
+use tables::{StatsTable, TableKind};
+
+mod chart;
+mod pause;
+mod query_log;
+mod search;
+mod tables;
+
+// Resolver latency measured from matched query/response pairs
+#[derive(Default)]
+struct LatencyStats {
+    count: u32,
+    total: Duration,
+    min: Option<Duration>,
+    max: Duration,
+}
+
+impl LatencyStats {
+    fn record(&mut self, rtt: Duration) {
+        self.count += 1;
+        self.total += rtt;
+        self.min = Some(self.min.map_or(rtt, |min| min.min(rtt)));
+        self.max = self.max.max(rtt);
+    }
+
+    fn average(&self) -> Option<Duration> {
+        (self.count > 0).then(|| self.total / self.count)
+    }
+
+    fn merge(&mut self, other: &LatencyStats) {
+        self.count += other.count;
+        self.total += other.total;
+        self.min = match (self.min, other.min) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.max = self.max.max(other.max);
+    }
+}
+
+// Traffic on DoT, DoH or DoQ sessions to one provider
+#[derive(Default)]
+struct EncryptedStats {
+    sessions: u32,
+    active: u32,
+    packets: u64,
+    bytes: u64,
+}
+
+// The fields a query is counted under, so that the panels can be narrowed by
+// the protocol filter and search after the fact
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct QueryKey {
+    protocol: DnsProtocol,
+    domain: String,
+    query_type: String,
+    provider: DnsProvider,
+    source: String,
+    destination: String,
+    // Name of the process that sent the query, when it could be found
+    process: Option<String>,
+}
+
+// Queries counted under a key, with the latest pid of the process that sent them
+struct QueryCount {
+    queries: u32,
+    pid: Option<u32>,
+    first_seen: SystemTime,
+    last_seen: SystemTime,
+}
+
+// The fields a response or timeout is counted under, like a QueryKey
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct OutcomeKey {
+    protocol: DnsProtocol,
+    domain: String,
+    query_type: String,
+    provider: DnsProvider,
+    source: String,
+    // The response code, or None for queries that timed out
+    rcode: Option<String>,
+}
+
+// Responses or timeouts counted under a key
+struct OutcomeCount {
+    count: u32,
+    latency: LatencyStats,
+    // When the latest of the queries was sent
+    last_seen: SystemTime,
+}
+
+// Responses and timeouts summed over several outcome keys
+#[derive(Default)]
+struct Outcomes {
+    latency: LatencyStats,
+    // Responses with a non-NOERROR rcode (NXDOMAIN, SERVFAIL, ...)
+    errors: u32,
+    timeouts: u32,
+}
+
+impl Outcomes {
+    fn add(&mut self, key: &OutcomeKey, count: &OutcomeCount) {
+        match &key.rcode {
+            Some(rcode) => {
+                self.latency.merge(&count.latency);
+                if rcode != "NOERROR" {
+                    self.errors += count.count;
+                }
+            }
+            None => self.timeouts += count.count,
+        }
+    }
+
+    // Queries that failed to resolve, with an error or by timing out
+    fn failures(&self) -> u32 {
+        self.errors + self.timeouts
+    }
+
+    // Describe latency and failures, e.g. "avg 12.3ms, min 4.0ms, max 80.1ms, 2 timeouts"
+    fn summary(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let (Some(avg), Some(min)) = (self.latency.average(), self.latency.min) {
+            parts.push(format!(
+                "avg {:.1}ms, min {:.1}ms, max {:.1}ms",
+                avg.as_secs_f64() * 1000.0,
+                min.as_secs_f64() * 1000.0,
+                self.latency.max.as_secs_f64() * 1000.0
+            ));
+        }
+        if self.errors > 0 {
+            parts.push(format!("{} errors", self.errors));
+        }
+        if self.timeouts > 0 {
+            parts.push(format!("{} timeouts", self.timeouts));
+        }
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+// The screens of the TUI, in tab order
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    Overview,
+    Table(TableKind),
+    Queries,
+}
+
+impl Tab {
+    const ALL: [Tab; 6] = [
+        Tab::Overview,
+        Tab::Table(TableKind::Domains),
+        Tab::Table(TableKind::Clients),
+        Tab::Table(TableKind::Resolvers),
+        Tab::Table(TableKind::Errors),
+        Tab::Queries,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            Tab::Overview => "Overview",
+            Tab::Table(kind) => kind.title(),
+            Tab::Queries => "Queries",
+        }
+    }
+
+    fn index(self) -> usize {
+        Tab::ALL.iter().position(|tab| *tab == self).unwrap_or(0)
+    }
+
+    // The tab `offset` places along, wrapping around
+    fn step(self, offset: isize) -> Self {
+        let len = Tab::ALL.len() as isize;
+        Tab::ALL[(self.index() as isize + offset).rem_euclid(len) as usize]
+    }
+}
+
+// Queries kept for the activity panel and query log
+const QUERY_LOG_SIZE: usize = 1000;
+
+// Chart windows of history kept, to scrub back through while paused
+const HISTORY_WINDOWS: f64 = 10.0;
+
+// What became of a query in the query log
+#[derive(Clone)]
+enum QueryOutcome {
+    Pending,
+    Answered {
+        rtt: Duration,
+        rcode: String,
+        // Extended DNS Errors explaining the rcode, e.g. "EDE 15 (Blocked)"
+        extended_errors: Vec<String>,
+        response: Box<DnsPacket>,
+    },
+    TimedOut,
+    // A link-local response logged on its own, with no query captured for it
+    Unsolicited,
+}
+
+// A query in the query log
+struct LoggedQuery {
+    // Identifies the query while it moves through the log
+    id: u64,
+    domain: String,
+    query_type: String,
+    protocol: DnsProtocol,
+    provider: DnsProvider,
+    source: String,
+    destination: String,
+    bypass: bool,
+    interface: Option<String>,
+    process: Option<ProcessInfo>,
+    timestamp: SystemTime,
+    // Seconds after the first packet seen that the query was captured, placing
+    // it on the same timeline as the chart
+    captured: f64,
+    // The whole query message, with every section
+    packet: Box<DnsPacket>,
+    outcome: QueryOutcome,
+}
+
+impl LoggedQuery {
+    fn resolver(&self) -> String {
+        resolver_name(self.protocol, self.provider)
+    }
+}
+
+// The provider queried, or for link-local queries, which have no provider,
+// the protocol
+fn resolver_name(protocol: DnsProtocol, provider: DnsProvider) -> String {
+    if protocol.is_unicast() {
+        provider.as_str().to_string()
+    } else {
+        protocol.to_string()
+    }
+}
 
 // Data structures for tracking DNS traffic
 struct DnsTrafficData {
-    // History of queries per provider over time (for charts)
-    provider_history: HashMap<DnsProvider, Vec<(f64, f64)>>, // (time_offset, count)
-    start_time: Instant,
-    window_size: f64, // Time window in seconds
+    // Queries per provider over time, for the chart
+    provider_history: HashMap<DnsProvider, RateHistory>,
+    // Capture time of the first packet seen, which the chart's timeline
+    // counts from, so a savefile plots as it was captured rather than read
+    first_capture: Option<SystemTime>,
+    // The newest capture time seen, in seconds on the timeline
+    latest_capture: f64,
+    // Whether packets are captured live, so the timeline keeps moving
+    // between them, rather than read from a savefile, where it stops at the last
+    live: bool,
+    window_size: f64, // Chart window in seconds
 
     // Current stats
     top_domains: Vec<(String, u32)>,
     top_providers: Vec<(DnsProvider, u32)>,
 
     // Current query counts
-    queries_per_provider: HashMap<DnsProvider, u32>,
-    domain_counts: HashMap<String, u32>,
-    provider_counts: HashMap<DnsProvider, u32>,
+    protocol_counts: HashMap<DnsProtocol, u32>,
+    // Queries by domain, type, resolver, source, destination and process,
+    // which the domain, provider, connection and process panels are summed from
+    query_counts: HashMap<QueryKey, QueryCount>,
+
+    // Interfaces queries were seen on, to label activity when there are several
+    interfaces: Vec<String>,
+
+    // Only show domains, connections and activity of this protocol
+    protocol_filter: Option<DnsProtocol>,
+    // Only show queries matching what's typed after `/`
+    search: Search,
+    // Hide reverse lookups, service discovery and connectivity checks
+    hide_noisy: bool,
+
+    // Set while the display is frozen, holding back new events
+    pause: Option<Pause>,
 
-    // Recent queries for detailed view
-    recent_queries: VecDeque<TxEvent>,
+    // Recent queries for detailed view, newest first
+    recent_queries: VecDeque<LoggedQuery>,
+    next_query_id: u64,
 
-    // Source-to-destination tracking
-    connections: HashMap<String, u32>,
+    // Responses and timeouts by query and rcode, which resolver latency and
+    // errors are summed from
+    outcome_counts: HashMap<OutcomeKey, OutcomeCount>,
+
+    // EDNS(0) usage across all queries
+    total_queries: u32,
+    edns_queries: u32,
+    dnssec_ok_queries: u32,
+    // Queries that disclosed part of the client's address via ECS
+    client_subnet_queries: u32,
+
+    // The host's configured resolvers, and queries sent anywhere else
+    system_resolvers: Vec<String>,
+    bypass_queries: u32,
+
+    // Encrypted DNS sessions per provider and protocol
+    encrypted: HashMap<(DnsProvider, String), EncryptedStats>,
 }
 
 impl DnsTrafficData {
-    fn new(window_size: f64) -> Self {
+    fn new(window_size: f64, live: bool) -> Self {
         Self {
             provider_history: HashMap::new(),
-            start_time: Instant::now(),
+            first_capture: None,
+            latest_capture: 0.0,
+            live,
             window_size,
             top_domains: Vec::new(),
             top_providers: Vec::new(),
-            queries_per_provider: HashMap::new(),
-            domain_counts: HashMap::new(),
-            provider_counts: HashMap::new(),
-            recent_queries: VecDeque::with_capacity(100), // Keep last 100 queries
-            connections: HashMap::new(),
+            protocol_counts: HashMap::new(),
+            query_counts: HashMap::new(),
+            protocol_filter: None,
+            search: Search::default(),
+            hide_noisy: false,
+            pause: None,
+            interfaces: Vec::new(),
+            recent_queries: VecDeque::with_capacity(QUERY_LOG_SIZE),
+            next_query_id: 0,
+            outcome_counts: HashMap::new(),
+            total_queries: 0,
+            edns_queries: 0,
+            dnssec_ok_queries: 0,
+            client_subnet_queries: 0,
+            system_resolvers: system_resolvers().iter().map(|ip| ip.to_string()).collect(),
+            bypass_queries: 0,
+            encrypted: HashMap::new(),
         }
     }
 
+    // Take in an event from the capture, or hold it back while paused
     fn update(&mut self, event: TxEvent) {
-        let event_clone = event.clone();
+        if let Some(pause) = &mut self.pause {
+            pause.queue(event);
+            return;
+        }
+        if self.apply(event) {
+            self.update_top_lists();
+            self.prune_old_data();
+        }
+    }
+
+    // Count an event. Returns whether it was a query, which the top lists need
+    // updating for.
+    fn apply(&mut self, event: TxEvent) -> bool {
         match event {
             TxEvent::DnsQuery {
                 domain,
+                query_type,
+                protocol,
                 provider,
                 source,
                 destination,
-                ..
+                bypass,
+                interface,
+                process,
+                edns,
+                timestamp,
+                packet,
             } => {
-                // Update domain counts
-                *self.domain_counts.entry(domain.clone()).or_insert(0) += 1;
-
-                // Update provider counts
-                *self.provider_counts.entry(provider.clone()).or_insert(0) += 1;
-
-                // Update query count for this provider (for chart)
-                *self
-                    .queries_per_provider
-                    .entry(provider.clone())
-                    .or_insert(0) += 1;
-
-                // Track connections (source to destination pairs)
-                let connection_key = format!("{}->{}", source, destination);
-                *self.connections.entry(connection_key).or_insert(0) += 1;
+                let captured = self.capture_time(timestamp);
+                self.total_queries += 1;
+                if let Some(interface) = &interface
+                    && !self.interfaces.contains(interface)
+                {
+                    self.interfaces.push(interface.clone());
+                }
+                *self.protocol_counts.entry(protocol).or_insert(0) += 1;
+                if bypass {
+                    self.bypass_queries += 1;
+                }
+                if let Some(edns) = &edns {
+                    self.edns_queries += 1;
+                    if edns.dnssec_ok {
+                        self.dnssec_ok_queries += 1;
+                    }
+                    if edns.client_subnet().is_some() {
+                        self.client_subnet_queries += 1;
+                    }
+                }
 
-                // Add data point for chart
-                let elapsed = self.start_time.elapsed().as_secs_f64();
-                let count = *self.queries_per_provider.get(&provider).unwrap_or(&0) as f64;
+                let count = self
+                    .query_counts
+                    .entry(QueryKey {
+                        protocol,
+                        domain: domain.clone(),
+                        query_type: query_type.clone(),
+                        provider,
+                        source: source.clone(),
+                        destination: destination.clone(),
+                        process: process.as_ref().map(|process| process.name.clone()),
+                    })
+                    .or_insert_with(|| QueryCount {
+                        queries: 0,
+                        pid: None,
+                        first_seen: timestamp,
+                        last_seen: timestamp,
+                    });
+                count.queries += 1;
+                count.first_seen = count.first_seen.min(timestamp);
+                count.last_seen = count.last_seen.max(timestamp);
+                if let Some(process) = &process {
+                    count.pid = Some(process.pid);
+                }
 
-                self.provider_history
-                    .entry(provider.clone())
-                    .or_insert_with(Vec::new)
-                    .push((elapsed, count));
+                // Link-local queries have no provider to chart
+                if protocol.is_unicast() {
+                    let window_size = self.window_size;
+                    self.provider_history
+                        .entry(provider)
+                        .or_insert_with(|| RateHistory::new(window_size))
+                        .record(captured);
+                }
 
                 // Store recent query
-                self.recent_queries.push_front(event_clone);
-                if self.recent_queries.len() > 100 {
+                self.next_query_id += 1;
+                self.recent_queries.push_front(LoggedQuery {
+                    id: self.next_query_id,
+                    domain,
+                    query_type,
+                    protocol,
+                    provider,
+                    source,
+                    destination,
+                    bypass,
+                    interface,
+                    process,
+                    timestamp,
+                    captured,
+                    packet,
+                    outcome: QueryOutcome::Pending,
+                });
+                if self.recent_queries.len() > QUERY_LOG_SIZE {
+                    self.recent_queries.pop_back();
+                }
+                return true;
+            }
+            TxEvent::DnsTransaction {
+                domain,
+                query_type,
+                protocol,
+                provider,
+                source,
+                destination,
+                rcode,
+                rtt,
+                edns,
+                timestamp,
+                response,
+            } => {
+                self.count_outcome(
+                    OutcomeKey {
+                        protocol,
+                        domain: domain.clone(),
+                        query_type: query_type.clone(),
+                        provider,
+                        source: source.clone(),
+                        rcode: Some(rcode.clone()),
+                    },
+                    timestamp,
+                    Some(rtt),
+                );
+                self.set_query_outcome(
+                    &domain,
+                    &query_type,
+                    &source,
+                    &destination,
+                    timestamp,
+                    QueryOutcome::Answered {
+                        rtt,
+                        rcode,
+                        extended_errors: edns
+                            .iter()
+                            .flat_map(|edns| edns.extended_errors())
+                            .map(|error| format!("EDE {} ({})", error.code, error.code_name()))
+                            .collect(),
+                        response,
+                    },
+                );
+            }
+            TxEvent::DnsResponse {
+                domain,
+                query_type,
+                protocol,
+                source,
+                destination,
+                interface,
+                timestamp,
+                response,
+            } => {
+                let captured = self.capture_time(timestamp);
+                if let Some(interface) = &interface
+                    && !self.interfaces.contains(interface)
+                {
+                    self.interfaces.push(interface.clone());
+                }
+                // Logged like a query so it's searched and filtered alike,
+                // but with the response in place of the query message
+                self.next_query_id += 1;
+                self.recent_queries.push_front(LoggedQuery {
+                    id: self.next_query_id,
+                    domain,
+                    query_type,
+                    protocol,
+                    provider: response.provider,
+                    source,
+                    destination,
+                    bypass: false,
+                    interface,
+                    process: None,
+                    timestamp,
+                    captured,
+                    packet: response,
+                    outcome: QueryOutcome::Unsolicited,
+                });
+                if self.recent_queries.len() > QUERY_LOG_SIZE {
                     self.recent_queries.pop_back();
                 }
+            }
+            TxEvent::DnsTimeout {
+                domain,
+                query_type,
+                protocol,
+                provider,
+                source,
+                destination,
+                timestamp,
+            } => {
+                self.count_outcome(
+                    OutcomeKey {
+                        protocol,
+                        domain: domain.clone(),
+                        query_type: query_type.clone(),
+                        provider,
+                        source: source.clone(),
+                        rcode: None,
+                    },
+                    timestamp,
+                    None,
+                );
+                self.set_query_outcome(
+                    &domain,
+                    &query_type,
+                    &source,
+                    &destination,
+                    timestamp,
+                    QueryOutcome::TimedOut,
+                );
+            }
+            TxEvent::EncryptedDnsSession {
+                provider,
+                protocol,
+                event,
+            } => {
+                let stats = self.encrypted.entry((provider, protocol)).or_default();
+                match event {
+                    SessionEvent::Opened => {
+                        stats.sessions += 1;
+                        stats.active += 1;
+                    }
+                    SessionEvent::Traffic { packets, bytes } => {
+                        stats.packets += packets;
+                        stats.bytes += bytes;
+                    }
+                    SessionEvent::Closed => stats.active = stats.active.saturating_sub(1),
+                }
+            }
+        }
+        false
+    }
 
-                // Update top lists after each query
-                self.update_top_lists();
+    // Place a packet captured at `timestamp` on the timeline, in seconds
+    // after the first packet seen
+    fn capture_time(&mut self, timestamp: SystemTime) -> f64 {
+        let first = *self.first_capture.get_or_insert(timestamp);
+        let captured = timestamp
+            .duration_since(first)
+            .map_or(0.0, |since| since.as_secs_f64());
+        self.latest_capture = self.latest_capture.max(captured);
+        captured
+    }
+
+    // The newest moment on the timeline: the present while capturing live, or
+    // the last packet read from a savefile
+    fn latest_time(&self) -> f64 {
+        match self.first_capture {
+            Some(first) if self.live => SystemTime::now()
+                .duration_since(first)
+                .map_or(0.0, |since| since.as_secs_f64())
+                .max(self.latest_capture),
+            _ => self.latest_capture,
+        }
+    }
+
+    // The moment the display shows on the timeline: the latest while running,
+    // or the point scrubbed back to while paused
+    fn now(&self) -> f64 {
+        self.pause
+            .as_ref()
+            .map_or_else(|| self.latest_time(), Pause::viewed_time)
+    }
 
-                // Prune old data points
+    // Freeze the display, or resume it and catch up on the events held back
+    fn toggle_pause(&mut self) {
+        match self.pause.take() {
+            Some(pause) => {
+                for event in pause.into_queued() {
+                    self.apply(event);
+                }
+                self.update_top_lists();
                 self.prune_old_data();
             }
+            None => self.pause = Some(Pause::new(self.latest_time())),
+        }
+    }
+
+    // Step the display back or forward through the retained history, a
+    // twelfth of the chart at a time. Stepping back pauses first.
+    fn scrub(&mut self, back: bool) {
+        if back && self.pause.is_none() {
+            self.toggle_pause();
+        }
+        let step = self.window_size / 12.0;
+        let limit = self.window_size * (HISTORY_WINDOWS - 1.0);
+        if let Some(pause) = &mut self.pause {
+            let limit = limit.min(pause.at());
+            pause.scrub(if back { step } else { -step }, limit);
+        }
+    }
+
+    fn count_outcome(&mut self, key: OutcomeKey, timestamp: SystemTime, rtt: Option<Duration>) {
+        let count = self
+            .outcome_counts
+            .entry(key)
+            .or_insert_with(|| OutcomeCount {
+                count: 0,
+                latency: LatencyStats::default(),
+                last_seen: timestamp,
+            });
+        count.count += 1;
+        count.last_seen = count.last_seen.max(timestamp);
+        if let Some(rtt) = rtt {
+            count.latency.record(rtt);
         }
     }
 
+    // Attach a response latency or timeout to the matching entry in the recent list
+    fn set_query_outcome(
+        &mut self,
+        domain: &str,
+        query_type: &str,
+        source: &str,
+        destination: &str,
+        timestamp: SystemTime,
+        outcome: QueryOutcome,
+    ) {
+        let entry = self.recent_queries.iter_mut().find(|query| {
+            query.domain == domain
+                && query.query_type == query_type
+                && query.source == source
+                && query.destination == destination
+                && query.timestamp == timestamp
+        });
+        if let Some(query) = entry {
+            query.outcome = outcome;
+        }
+    }
+
+    // Describe EDNS usage, e.g. "EDNS 40/50, DO 12, ECS 1"
+    fn edns_summary(&self) -> String {
+        format!(
+            "EDNS {}/{}, DO {}, ECS {}",
+            self.edns_queries,
+            self.total_queries,
+            self.dnssec_ok_queries,
+            self.client_subnet_queries
+        )
+    }
+
+    // Whether events of `protocol` pass the protocol filter
+    fn shows_protocol(&self, protocol: DnsProtocol) -> bool {
+        self.protocol_filter.is_none_or(|filter| filter == protocol)
+    }
+
+    // Whether a query passes the protocol filter, the noisy suffix toggle and
+    // the search
+    fn shows_query(
+        &self,
+        protocol: DnsProtocol,
+        domain: &str,
+        query_type: &str,
+        provider: DnsProvider,
+        source: &str,
+    ) -> bool {
+        self.shows_protocol(protocol)
+            && !(self.hide_noisy && is_noisy(domain))
+            && self
+                .search
+                .matches(&[domain, provider.as_str(), source, query_type])
+    }
+
+    // Whether a logged query passes the filters and, when scrubbed back, had
+    // arrived by the moment shown
+    fn shows_logged(&self, query: &LoggedQuery) -> bool {
+        query.captured <= self.now()
+            && self.shows_query(
+                query.protocol,
+                &query.domain,
+                &query.query_type,
+                query.provider,
+                &query.source,
+            )
+    }
+
+    fn shows(&self, key: &QueryKey) -> bool {
+        self.shows_query(
+            key.protocol,
+            &key.domain,
+            &key.query_type,
+            key.provider,
+            &key.source,
+        )
+    }
+
+    fn shows_outcome(&self, key: &OutcomeKey) -> bool {
+        self.shows_query(
+            key.protocol,
+            &key.domain,
+            &key.query_type,
+            key.provider,
+            &key.source,
+        )
+    }
+
+    fn toggle_noisy(&mut self) {
+        self.hide_noisy = !self.hide_noisy;
+        self.update_top_lists();
+    }
+
+    // Apply a key press to the search prompt while it's open
+    fn edit_search(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Char(c) => self.search.push(c),
+            KeyCode::Backspace => self.search.pop(),
+            KeyCode::Enter => self.search.finish(),
+            KeyCode::Esc => self.search.clear(),
+            _ => return,
+        }
+        // Narrow the panels as the search is typed
+        self.update_top_lists();
+    }
+
+    fn clear_search(&mut self) {
+        self.search.clear();
+        self.update_top_lists();
+    }
+
+    // Step the protocol filter through all protocols and back to none
+    fn cycle_protocol_filter(&mut self) {
+        self.protocol_filter = match self.protocol_filter {
+            None => Some(DnsProtocol::ALL[0]),
+            Some(current) => DnsProtocol::ALL
+                .iter()
+                .position(|protocol| *protocol == current)
+                .and_then(|i| DnsProtocol::ALL.get(i + 1))
+                .copied(),
+        };
+        self.update_top_lists();
+    }
+
+    // Title suffix naming the filters in effect, e.g. " [mDNS] [/amazon] [-noisy]"
+    fn filter_indicator(&self) -> String {
+        let mut indicator = self
+            .protocol_filter
+            .map(|protocol| format!(" [{}]", protocol))
+            .unwrap_or_default();
+        if self.search.is_active() {
+            indicator.push_str(&format!(" [/{}]", self.search.text().trim()));
+        }
+        if self.hide_noisy {
+            indicator.push_str(" [-noisy]");
+        }
+        indicator
+    }
+
+    // Sum the counts of the shown queries by `label`, busiest first. Queries
+    // without a label aren't counted.
+    fn top_counts<K, F>(&self, limit: usize, label: F) -> Vec<(K, u32)>
+    where
+        K: Hash + Eq,
+        F: Fn(&QueryKey) -> Option<K>,
+    {
+        let mut counts: HashMap<K, u32> = HashMap::new();
+        for (key, count) in &self.query_counts {
+            if self.shows(key)
+                && let Some(label) = label(key)
+            {
+                *counts.entry(label).or_insert(0) += count.queries;
+            }
+        }
+        let mut counts: Vec<(K, u32)> = counts.into_iter().collect();
+        counts.sort_by_key(|b| std::cmp::Reverse(b.1)); // Sort by count descending
+        counts.truncate(limit);
+        counts
+    }
+
+    // Sum the responses and timeouts of the shown queries by `label`
+    fn outcomes_by<K, F>(&self, label: F) -> HashMap<K, Outcomes>
+    where
+        K: Hash + Eq,
+        F: Fn(&OutcomeKey) -> Option<K>,
+    {
+        let mut outcomes: HashMap<K, Outcomes> = HashMap::new();
+        for (key, count) in &self.outcome_counts {
+            if self.shows_outcome(key)
+                && let Some(label) = label(key)
+            {
+                outcomes.entry(label).or_default().add(key, count);
+            }
+        }
+        outcomes
+    }
+
     fn update_top_lists(&mut self) {
-        // Update top domains
-        self.top_domains = self
-            .domain_counts
-            .iter()
-            .map(|(domain, count)| (domain.clone(), *count))
-            .collect::<Vec<_>>();
-        self.top_domains.sort_by(|a, b| b.1.cmp(&a.1)); // Sort by count descending
-        self.top_domains.truncate(10); // Keep top 10
-
-        // Update top providers
-        self.top_providers = self
-            .provider_counts
-            .iter()
-            .map(|(provider, count)| (provider.clone(), *count))
-            .collect::<Vec<_>>();
-        self.top_providers.sort_by(|a, b| b.1.cmp(&a.1)); // Sort by count descending
-        self.top_providers.truncate(5); // Keep top 5
+        self.top_domains = self.top_counts(10, |key| Some(key.domain.clone()));
+        // Link-local queries have no resolver
+        self.top_providers =
+            self.top_counts(5, |key| key.protocol.is_unicast().then_some(key.provider));
     }
 
     fn prune_old_data(&mut self) {
-        let current_time = self.start_time.elapsed().as_secs_f64();
-        let cutoff = current_time - self.window_size;
+        let cutoff = self.latest_time() - self.window_size * HISTORY_WINDOWS;
+
+        for history in self.provider_history.values_mut() {
+            history.prune(cutoff);
+        }
+    }
+
+    // Queries per protocol and the filter key, e.g. "DNS 120, mDNS 30"
+    fn protocol_spans(&self) -> Vec<Span<'static>> {
+        let counts: Vec<String> = DnsProtocol::ALL
+            .iter()
+            .filter_map(|protocol| {
+                self.protocol_counts
+                    .get(protocol)
+                    .map(|count| format!("{} {}", protocol, count))
+            })
+            .collect();
+        let filter = self
+            .protocol_filter
+            .map_or("all".to_string(), |protocol| protocol.to_string());
+
+        vec![
+            Span::styled(
+                format!("  Queries: {}", counts.join(", ")),
+                Style::default().fg(Color::White),
+            ),
+            Span::styled(
+                format!("  [p] protocol: {}", filter),
+                Style::default().fg(Color::Gray),
+            ),
+        ]
+    }
+
+    // Summary of the host's resolvers and queries that went around them
+    fn header_line(&self) -> Line<'static> {
+        let title = Span::styled(
+            " DustCloud ",
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        );
+
+        if self.system_resolvers.is_empty() {
+            let mut spans = vec![
+                title,
+                Span::styled(
+                    " System resolvers: not detected",
+                    Style::default().fg(Color::Gray),
+                ),
+            ];
+            spans.extend(self.protocol_spans());
+            return Line::from(spans);
+        }
+
+        let bypass_style = if self.bypass_queries > 0 {
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Green)
+        };
+        let mut spans = vec![
+            title,
+            Span::styled(
+                format!(" System resolvers: {}", self.system_resolvers.join(", ")),
+                Style::default().fg(Color::White),
+            ),
+            Span::styled(
+                format!("  Bypass queries: {}", self.bypass_queries),
+                bypass_style,
+            ),
+        ];
+        spans.extend(self.protocol_spans());
+        Line::from(spans)
+    }
+
+    // The search prompt while it's open, otherwise the keys for filtering and
+    // for the current view
+    fn status_line(&self, view_keys: &str) -> Line<'static> {
+        let key_style = Style::default().fg(Color::Gray);
+        if self.search.is_editing() {
+            return Line::from(vec![
+                Span::styled(
+                    format!("/{}", self.search.text()),
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled("█", Style::default().fg(Color::Yellow)),
+                Span::styled(
+                    "  qname, provider, source or type; -word to exclude  [Enter] keep  [Esc] clear",
+                    key_style,
+                ),
+            ]);
+        }
 
-        for points in self.provider_history.values_mut() {
-            // Keep only points newer than cutoff
-            points.retain(|(time, _)| *time >= cutoff);
+        let mut spans = Vec::new();
+        if self.search.is_active() {
+            spans.push(Span::styled(
+                format!(" Search: {} ", self.search.text().trim()),
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ));
+            spans.push(Span::styled("  [Esc] clear", key_style));
+        }
+        match &self.pause {
+            Some(pause) => {
+                spans.push(Span::styled(
+                    " PAUSED ",
+                    Style::default()
+                        .fg(Color::Black)
+                        .bg(Color::Red)
+                        .add_modifier(Modifier::BOLD),
+                ));
+                let mut queued = format!(" {} queued", pause.queued());
+                if pause.dropped() > 0 {
+                    queued.push_str(&format!(", {} dropped", pause.dropped()));
+                }
+                if pause.offset() > 0.0 {
+                    queued.push_str(&format!(
+                        ", showing {} earlier",
+                        format_span(pause.offset())
+                    ));
+                }
+                spans.push(Span::styled(queued, Style::default().fg(Color::Red)));
+                spans.push(Span::styled("  [space] resume  [←/→] scrub", key_style));
+            }
+            None => spans.push(Span::styled("  [space] pause  [←] scrub", key_style)),
         }
+        spans.push(Span::styled(view_keys.to_string(), key_style));
+        spans.push(Span::styled("  [/] search", key_style));
+        spans.push(Span::styled(
+            format!(
+                "  [n] noisy suffixes: {}",
+                if self.hide_noisy { "hidden" } else { "shown" }
+            ),
+            key_style,
+        ));
+        spans.push(Span::styled("  [q] quit", key_style));
+        Line::from(spans)
+    }
+
+    // Encrypted DNS traffic by provider and protocol, busiest first
+    fn get_encrypted_sessions(&self) -> Vec<(DnsProvider, String, &EncryptedStats)> {
+        let mut sessions: Vec<_> = self
+            .encrypted
+            .iter()
+            .map(|((provider, protocol), stats)| (*provider, protocol.clone(), stats))
+            .collect();
+        sessions.sort_by_key(|(_, _, stats)| std::cmp::Reverse(stats.bytes));
+        sessions
     }
 
     // Get top source-destination connections
     fn get_top_connections(&self, limit: usize) -> Vec<(String, u32)> {
-        let mut connections: Vec<(String, u32)> = self.connections.clone().into_iter().collect();
-        connections.sort_by(|a, b| b.1.cmp(&a.1));
-        connections.truncate(limit);
-        connections
+        self.top_counts(limit, |key| {
+            Some(format!("{}->{}", key.source, key.destination))
+        })
+    }
+
+    // Get the processes sending the most queries, as (name, queries, latest pid)
+    fn get_top_processes(&self, limit: usize) -> Vec<(String, u32, u32)> {
+        let mut processes: HashMap<&str, (u32, u32)> = HashMap::new();
+        for (key, count) in &self.query_counts {
+            if self.shows(key)
+                && let (Some(name), Some(pid)) = (&key.process, count.pid)
+            {
+                let entry = processes.entry(name).or_insert((0, pid));
+                entry.0 += count.queries;
+            }
+        }
+        let mut processes: Vec<(String, u32, u32)> = processes
+            .into_iter()
+            .map(|(name, (count, pid))| (name.to_string(), count, pid))
+            .collect();
+        processes.sort_by_key(|b| std::cmp::Reverse(b.1));
+        processes.truncate(limit);
+        processes
     }
 
     // Get recent DNS queries as formatted strings
     fn get_recent_activity(&self, limit: usize) -> Vec<String> {
         self.recent_queries
             .iter()
-            .take(limit)
-            .map(|event| match event {
-                TxEvent::DnsQuery {
-                    domain,
-                    query_type,
-                    provider,
-                    timestamp,
-                    ..
-                } => {
-                    let time_since_start = timestamp
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_secs()
-                        % 86400; // Seconds in current day
-
-                    let hours = (time_since_start / 3600) % 24;
-                    let minutes = (time_since_start / 60) % 60;
-                    let seconds = time_since_start % 60;
-
-                    format!(
-                        "{:02}:{:02}:{:02} - {} - {} ({})",
-                        hours,
-                        minutes,
-                        seconds,
-                        domain,
-                        query_type,
-                        provider.as_str()
-                    )
+            .filter(|query| self.shows_logged(query))
+            .map(|query| {
+                let mut status = match &query.outcome {
+                    QueryOutcome::Pending => String::new(),
+                    QueryOutcome::Answered { rtt, rcode, .. } if rcode == "NOERROR" => {
+                        format!(" {:.1}ms", rtt.as_secs_f64() * 1000.0)
+                    }
+                    QueryOutcome::Answered { rtt, rcode, .. } => {
+                        format!(" {} {:.1}ms", rcode, rtt.as_secs_f64() * 1000.0)
+                    }
+                    QueryOutcome::TimedOut => " timed out".to_string(),
+                    QueryOutcome::Unsolicited => " response".to_string(),
+                };
+                if let QueryOutcome::Answered {
+                    extended_errors, ..
+                } = &query.outcome
+                {
+                    for error in extended_errors {
+                        status.push_str(&format!(" [{}]", error));
+                    }
                 }
+
+                let subnet = query
+                    .packet
+                    .edns
+                    .as_ref()
+                    .and_then(|edns| edns.client_subnet())
+                    .map(|subnet| format!(" [ECS {}/{}]", subnet.address, subnet.source_prefix))
+                    .unwrap_or_default();
+
+                let interface = match &query.interface {
+                    Some(interface) if self.interfaces.len() > 1 => {
+                        format!(" [{}]", interface)
+                    }
+                    _ => String::new(),
+                };
+
+                let process = query
+                    .process
+                    .as_ref()
+                    .map(|process| format!(" by {}", process.name))
+                    .unwrap_or_default();
+
+                format!(
+                    "{}{} - {} - {} ({}{}){}{}{}",
+                    format_time(query.timestamp),
+                    interface,
+                    query.domain,
+                    query.query_type,
+                    query.resolver(),
+                    if query.bypass { ", bypass" } else { "" },
+                    process,
+                    subnet,
+                    status
+                )
             })
+            .take(limit)
             .collect()
     }
 }
 
-pub fn run_tui(rx: Receiver<TxEvent>) -> Result<(), io::Error> {
+/// Puts the terminal back how it was found when dropped, so that it's
+/// restored however the TUI exits
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// Leave raw mode and the alternate screen. Errors are ignored, as this runs
+/// while exiting and there's nothing better to do with them.
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        Show
+    );
+}
+
+/// Draw the dashboard until it's quit with q or Ctrl+C, or a shutdown is
+/// requested, e.g. by SIGTERM or the capture failing
+pub fn run_tui(rx: Receiver<TxEvent>, window: Duration, live: bool) -> Result<(), io::Error> {
+    // A panic on any thread restores the terminal before its message is
+    // printed, and stops the capture
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        shutdown::request();
+        default_hook(info);
+    }));
+
     // Setup terminal
     enable_raw_mode()?;
+    let _guard = TerminalGuard;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut traffic_data = DnsTrafficData::new(60.0); // 60 second window
+    let mut traffic_data = DnsTrafficData::new(window.as_secs_f64(), live);
+    let mut tab = Tab::Overview;
+    let mut query_log = QueryLog::default();
+    let mut tables = [
+        TableKind::Domains,
+        TableKind::Clients,
+        TableKind::Resolvers,
+        TableKind::Errors,
+    ]
+    .map(StatsTable::new);
 
     let tick_rate = Duration::from_millis(100);
     let mut last_tick = Instant::now();
 
-    loop {
+    while !shutdown::requested() {
         // Draw UI
         terminal.draw(|f| {
-            // Create layout
-            let chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
-                .split(f.area());
-
-            // Stats area
-            let stats_chunks = Layout::default()
+            // Header line and tabs above the panels, and the search prompt
+            // below them
+            let rows = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints([
-                    Constraint::Percentage(30),
-                    Constraint::Percentage(30),
-                    Constraint::Percentage(40),
+                    Constraint::Length(1),
+                    Constraint::Length(1),
+                    Constraint::Min(0),
+                    Constraint::Length(1),
                 ])
-                .split(chunks[0]);
-
-            // 1. Render top domains list
-            let domains: Vec<ListItem> = traffic_data
-                .top_domains
-                .iter()
-                .map(|(domain, count)| {
-                    ListItem::new(format!("{}: {}", domain, count))
-                        .style(Style::default().fg(Color::White))
-                })
-                .collect();
-
-            let domains_list = List::new(domains)
-                .block(
-                    Block::default()
-                        .title(Span::styled(
-                            "Top Domains",
-                            Style::default()
-                                .fg(Color::Cyan)
-                                .add_modifier(Modifier::BOLD),
-                        ))
-                        .borders(Borders::ALL)
-                        .border_style(Style::default().fg(Color::Gray)),
-                )
-                .highlight_style(Style::default().add_modifier(Modifier::BOLD))
-                .highlight_symbol(">> ");
-
-            f.render_widget(domains_list, stats_chunks[0]);
-
-            // 2. Render top providers list
-            let providers: Vec<ListItem> = traffic_data
-                .top_providers
-                .iter()
-                .map(|(provider, count)| {
-                    // Choose a unique color for each provider if possible
-                    let color = match provider {
-                        DnsProvider::Cloudflare => Color::Cyan,
-                        DnsProvider::Google => Color::Red,
-                        DnsProvider::OpenDNS => Color::Green,
-                        DnsProvider::Quad9 => Color::Magenta,
-                        DnsProvider::AdGuard => Color::Yellow,
-                        DnsProvider::CleanBrowsing => Color::White,
-                        DnsProvider::Unknown => Color::Gray,
-                    };
-
-                    ListItem::new(format!("{}: {}", provider.as_str(), count))
-                        .style(Style::default().fg(color))
-                })
-                .collect();
-
-            let providers_list = List::new(providers)
-                .block(
-                    Block::default()
-                        .title(Span::styled(
-                            "Top Providers",
-                            Style::default()
-                                .fg(Color::Green)
-                                .add_modifier(Modifier::BOLD),
-                        ))
-                        .borders(Borders::ALL)
-                        .border_style(Style::default().fg(Color::Gray)),
-                )
-                .highlight_style(Style::default().add_modifier(Modifier::BOLD))
-                .highlight_symbol(">> ");
-
-            f.render_widget(providers_list, stats_chunks[1]);
-
-            // 3. Render connections list
-            let connections: Vec<ListItem> = traffic_data
-                .get_top_connections(5)
-                .iter()
-                .map(|(conn, count)| {
-                    ListItem::new(format!("{}: {}", conn, count))
-                        .style(Style::default().fg(Color::Yellow))
-                })
-                .collect();
-
-            let connections_list = List::new(connections)
-                .block(
-                    Block::default()
-                        .title(Span::styled(
-                            "Top Connections",
-                            Style::default()
-                                .fg(Color::Yellow)
-                                .add_modifier(Modifier::BOLD),
-                        ))
-                        .borders(Borders::ALL)
-                        .border_style(Style::default().fg(Color::Gray)),
-                )
-                .highlight_style(Style::default().add_modifier(Modifier::BOLD))
-                .highlight_symbol(">> ");
-
-            f.render_widget(connections_list, stats_chunks[2]);
-
-            // Chart area
-            let chart_chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
-                .split(chunks[1]);
-
-            // DNS Traffic Chart
-            let datasets = create_chart_datasets(&traffic_data);
-
-            let chart = Chart::new(datasets)
-                .block(
-                    Block::default()
-                        .title(Span::styled(
-                            "DNS Traffic by Provider",
-                            Style::default()
-                                .fg(Color::Blue)
-                                .add_modifier(Modifier::BOLD),
-                        ))
-                        .borders(Borders::ALL)
-                        .border_style(Style::default().fg(Color::Gray)),
-                )
-                .x_axis(
-                    Axis::default()
-                        .title(Span::styled("Time (s)", Style::default().fg(Color::White)))
-                        .style(Style::default().fg(Color::White))
-                        .bounds([
-                            traffic_data.start_time.elapsed().as_secs_f64()
-                                - traffic_data.window_size,
-                            traffic_data.start_time.elapsed().as_secs_f64(),
-                        ])
-                        .labels(vec![
-                            Span::styled("-60s", Style::default().fg(Color::White)),
-                            Span::styled("now", Style::default().fg(Color::White)),
-                        ]),
-                )
-                .y_axis(
-                    Axis::default()
-                        .title(Span::styled("Queries", Style::default().fg(Color::White)))
-                        .style(Style::default().fg(Color::White))
-                        .bounds([0.0, 50.0])
-                        .labels(vec![
-                            Span::styled("0", Style::default().fg(Color::White)),
-                            Span::styled("25", Style::default().fg(Color::White)),
-                            Span::styled("50", Style::default().fg(Color::White)),
-                        ]),
-                );
+                .split(f.area());
 
-            f.render_widget(chart, chart_chunks[0]);
+            f.render_widget(Paragraph::new(traffic_data.header_line()), rows[0]);
+            f.render_widget(tab_bar(tab), rows[1]);
+            let tab_keys = match tab {
+                Tab::Overview => "",
+                Tab::Table(kind) => tables[kind as usize].help(),
+                Tab::Queries => query_log.help(),
+            };
+            f.render_widget(Paragraph::new(traffic_data.status_line(tab_keys)), rows[3]);
 
-            // Recent DNS Activity
-            let recent_activity = traffic_data.get_recent_activity(8);
-            let activity_items: Vec<ListItem> = recent_activity
-                .iter()
-                .map(|item| ListItem::new(item.clone()).style(Style::default().fg(Color::White)))
-                .collect();
-
-            let activity_list = List::new(activity_items).block(
-                Block::default()
-                    .title(Span::styled(
-                        "Recent DNS Activity",
-                        Style::default()
-                            .fg(Color::Magenta)
-                            .add_modifier(Modifier::BOLD),
-                    ))
-                    .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Gray)),
-            );
-
-            f.render_widget(activity_list, chart_chunks[1]);
+            match tab {
+                Tab::Overview => render_overview(f, rows[2], &traffic_data),
+                Tab::Table(kind) => {
+                    tables::render(f, rows[2], &traffic_data, &mut tables[kind as usize])
+                }
+                Tab::Queries => query_log::render(f, rows[2], &traffic_data, &mut query_log),
+            }
         })?;
 
         // Handle events
@@ -386,10 +1199,41 @@ pub fn run_tui(rx: Receiver<TxEvent>) -> Result<(), io::Error> {
             .checked_sub(last_tick.elapsed())
             .unwrap_or_else(|| Duration::from_secs(0));
 
-        if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                if key.code == KeyCode::Char('q') {
-                    break;
+        if crossterm::event::poll(timeout)?
+            && let Event::Key(key) = event::read()?
+        {
+            match key.code {
+                // Raw mode turns Ctrl+C into a key press rather than SIGINT
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+                // Everything else typed goes into the search while it's open
+                code if traffic_data.search.is_editing() => traffic_data.edit_search(code),
+                KeyCode::Char('q') => break,
+                KeyCode::Char('p') => traffic_data.cycle_protocol_filter(),
+                KeyCode::Char('/') => traffic_data.search.start(),
+                KeyCode::Char('n') => traffic_data.toggle_noisy(),
+                KeyCode::Char(' ') => traffic_data.toggle_pause(),
+                KeyCode::Left => traffic_data.scrub(true),
+                KeyCode::Right => traffic_data.scrub(false),
+                KeyCode::Tab => tab = tab.step(1),
+                KeyCode::BackTab => tab = tab.step(-1),
+                KeyCode::Char(digit @ '1'..='9') => {
+                    if let Some(selected) = digit
+                        .to_digit(10)
+                        .and_then(|digit| Tab::ALL.get(digit as usize - 1))
+                    {
+                        tab = *selected;
+                    }
+                }
+                code => {
+                    let handled = match tab {
+                        Tab::Overview => false,
+                        Tab::Table(kind) => tables[kind as usize].handle_key(&traffic_data, code),
+                        Tab::Queries => query_log.handle_key(&traffic_data, code),
+                    };
+                    // Unless it closed the query details
+                    if !handled && code == KeyCode::Esc {
+                        traffic_data.clear_search();
+                    }
                 }
             }
         }
@@ -404,45 +1248,271 @@ pub fn run_tui(rx: Receiver<TxEvent>) -> Result<(), io::Error> {
         }
     }
 
-    // Restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
-
     Ok(())
 }
 
-fn create_chart_datasets(data: &DnsTrafficData) -> Vec<Dataset> {
-    let mut datasets = Vec::new();
-
-    // Define colors for each provider
-    let provider_colors = [
-        (DnsProvider::Cloudflare, Color::Cyan),
-        (DnsProvider::Google, Color::Red),
-        (DnsProvider::OpenDNS, Color::Green),
-        (DnsProvider::Quad9, Color::Magenta),
-        (DnsProvider::AdGuard, Color::Yellow),
-        (DnsProvider::Unknown, Color::Gray),
-    ];
+// The tabs, numbered with the keys that select them
+fn tab_bar(tab: Tab) -> Tabs<'static> {
+    Tabs::new(
+        Tab::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, tab)| format!("{} {}", i + 1, tab.title())),
+    )
+    .select(tab.index())
+    .style(Style::default().fg(Color::Gray))
+    .highlight_style(
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD | Modifier::REVERSED),
+    )
+}
 
-    for (provider, color) in provider_colors.iter() {
-        // Convert string to DnsProvider enum
-        if let Some(history) = data.provider_history.get(&provider) {
-            if !history.is_empty() {
-                datasets.push(
-                    Dataset::default()
-                        .name(provider.as_str())
-                        .marker(symbols::Marker::Braille)
-                        .style(Style::default().fg(*color))
-                        .data(history),
-                );
-            }
-        }
+// The dashboard: top lists down the left, the traffic chart and recent
+// activity on the right
+fn render_overview(f: &mut Frame, area: Rect, traffic_data: &DnsTrafficData) {
+    // Create layout
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+        .split(area);
+
+    // Stats area
+    let stats_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+        ])
+        .split(chunks[0]);
+
+    // 1. Render top domains list
+    let domains: Vec<ListItem> = traffic_data
+        .top_domains
+        .iter()
+        .map(|(domain, count)| {
+            ListItem::new(format!("{}: {}", domain, count)).style(Style::default().fg(Color::White))
+        })
+        .collect();
+
+    let domains_list = List::new(domains)
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    format!("Top Domains{}", traffic_data.filter_indicator()),
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Gray)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+
+    f.render_widget(domains_list, stats_chunks[0]);
+
+    // 2. Render top providers list
+    let outcomes =
+        traffic_data.outcomes_by(|key| key.protocol.is_unicast().then_some(key.provider));
+    let providers: Vec<ListItem> = traffic_data
+        .top_providers
+        .iter()
+        .map(|(provider, count)| {
+            let text = match outcomes.get(provider).and_then(Outcomes::summary) {
+                Some(latency) => format!("{}: {} ({})", provider.as_str(), count, latency),
+                None => format!("{}: {}", provider.as_str(), count),
+            };
+            ListItem::new(text).style(Style::default().fg(provider.color()))
+        })
+        .collect();
+
+    let providers_list = List::new(providers)
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    format!("Top Providers{}", traffic_data.filter_indicator()),
+                    Style::default()
+                        .fg(Color::Green)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Gray)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+
+    f.render_widget(providers_list, stats_chunks[1]);
+
+    // 3. Render connections list
+    let connections: Vec<ListItem> = traffic_data
+        .get_top_connections(5)
+        .iter()
+        .map(|(conn, count)| {
+            ListItem::new(format!("{}: {}", conn, count)).style(Style::default().fg(Color::Yellow))
+        })
+        .collect();
+
+    let connections_list = List::new(connections)
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    format!("Top Connections{}", traffic_data.filter_indicator()),
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Gray)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+
+    f.render_widget(connections_list, stats_chunks[2]);
+
+    // 4. Render the processes sending queries
+    let mut processes: Vec<ListItem> = traffic_data
+        .get_top_processes(5)
+        .iter()
+        .map(|(name, count, pid)| {
+            ListItem::new(format!("{} (pid {}): {}", name, pid, count))
+                .style(Style::default().fg(Color::LightBlue))
+        })
+        .collect();
+    if processes.is_empty() {
+        processes.push(
+            ListItem::new("None identified (live capture on Linux only)")
+                .style(Style::default().fg(Color::Gray)),
+        );
     }
 
-    datasets
+    let processes_list = List::new(processes).block(
+        Block::default()
+            .title(Span::styled(
+                format!("Top Processes{}", traffic_data.filter_indicator()),
+                Style::default()
+                    .fg(Color::LightBlue)
+                    .add_modifier(Modifier::BOLD),
+            ))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Gray)),
+    );
+
+    f.render_widget(processes_list, stats_chunks[3]);
+
+    // 5. Render encrypted DNS sessions, whose queries can't be seen
+    let encrypted: Vec<ListItem> = traffic_data
+        .get_encrypted_sessions()
+        .iter()
+        .map(|(provider, protocol, stats)| {
+            ListItem::new(format!(
+                "{} {}: {} sessions ({} open), {} pkts, {}",
+                provider.as_str(),
+                protocol,
+                stats.sessions,
+                stats.active,
+                stats.packets,
+                format_bytes(stats.bytes)
+            ))
+            .style(Style::default().fg(provider.color()))
+        })
+        .collect();
+
+    let encrypted_list = List::new(encrypted).block(
+        Block::default()
+            .title(Span::styled(
+                "Encrypted DNS",
+                Style::default()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD),
+            ))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Gray)),
+    );
+
+    f.render_widget(encrypted_list, stats_chunks[4]);
+
+    // Chart area
+    let chart_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+        .split(chunks[1]);
+
+    chart::render(f, chart_chunks[0], traffic_data);
+
+    // Recent DNS Activity, as much as fits inside the borders
+    let recent_activity =
+        traffic_data.get_recent_activity(chart_chunks[1].height.saturating_sub(2) as usize);
+    let activity_items: Vec<ListItem> = recent_activity
+        .iter()
+        .map(|item| ListItem::new(item.clone()).style(Style::default().fg(Color::White)))
+        .collect();
+
+    let activity_list = List::new(activity_items).block(
+        Block::default()
+            .title(Span::styled(
+                format!(
+                    "Recent DNS Activity{} ({})",
+                    traffic_data.filter_indicator(),
+                    traffic_data.edns_summary()
+                ),
+                Style::default()
+                    .fg(Color::Magenta)
+                    .add_modifier(Modifier::BOLD),
+            ))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Gray)),
+    );
+
+    f.render_widget(activity_list, chart_chunks[1]);
+}
+
+// Time of day a packet was captured, e.g. "14:03:27"
+fn format_time(timestamp: SystemTime) -> String {
+    let time_since_start = timestamp
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        % 86400; // Seconds in current day
+
+    let hours = (time_since_start / 3600) % 24;
+    let minutes = (time_since_start / 60) % 60;
+    let seconds = time_since_start % 60;
+    format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+}
+
+// A length of time to the second, e.g. "90s" as "1m30s"
+fn format_span(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    let mut text = String::new();
+    if hours > 0 {
+        text.push_str(&format!("{}h", hours));
+    }
+    if minutes > 0 {
+        text.push_str(&format!("{}m", minutes));
+    }
+    if seconds > 0 || text.is_empty() {
+        text.push_str(&format!("{}s", seconds));
+    }
+    text
+}
+
+// Human-readable byte count, e.g. "1.5 KiB"
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
 }
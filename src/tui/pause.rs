@@ -0,0 +1,70 @@
+use std::collections::VecDeque;
+
+use crate::shared::TxEvent;
+
+// Events held while paused. Past this, newer ones are dropped rather than
+// letting a long pause use up memory.
+const MAX_QUEUED: usize = 100_000;
+
+// The display frozen at the moment it was paused, with the events that have
+// arrived since held back until it's resumed
+pub struct Pause {
+    // Point on the timeline it was paused at, in seconds after the first packet
+    at: f64,
+    // How far before `at` the display has been scrubbed back, in seconds
+    offset: f64,
+    // Events in the order they arrived
+    queued: VecDeque<TxEvent>,
+    dropped: u64,
+}
+
+impl Pause {
+    pub fn new(at: f64) -> Self {
+        Self {
+            at,
+            offset: 0.0,
+            queued: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    pub fn at(&self) -> f64 {
+        self.at
+    }
+
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    // The moment being shown, in seconds after the first packet
+    pub fn viewed_time(&self) -> f64 {
+        self.at - self.offset
+    }
+
+    // Move the display `seconds` further back, or forward when negative,
+    // staying between the pause and `limit` seconds before it
+    pub fn scrub(&mut self, seconds: f64, limit: f64) {
+        self.offset = (self.offset + seconds).clamp(0.0, limit.max(0.0));
+    }
+
+    pub fn queue(&mut self, event: TxEvent) {
+        if self.queued.len() < MAX_QUEUED {
+            self.queued.push_back(event);
+        } else {
+            self.dropped += 1;
+        }
+    }
+
+    pub fn queued(&self) -> usize {
+        self.queued.len()
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    // The held events, to apply on resuming
+    pub fn into_queued(self) -> VecDeque<TxEvent> {
+        self.queued
+    }
+}
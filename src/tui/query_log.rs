@@ -0,0 +1,537 @@
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Wrap},
+    Frame,
+};
+use std::{
+    cmp::Ordering,
+    net::SocketAddr,
+    time::{Duration, SystemTime},
+};
+
+use super::{format_time, DnsTrafficData, LoggedQuery, QueryOutcome};
+use crate::{
+    dns::{DnsPacket, DnsRecord},
+    net::ChecksumStatus,
+};
+
+// Columns the query log can be sorted by, in the order `s` steps through them
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SortColumn {
+    Time,
+    Domain,
+    Type,
+    Resolver,
+    Source,
+    Status,
+    Latency,
+}
+
+impl SortColumn {
+    const ALL: [SortColumn; 7] = [
+        SortColumn::Time,
+        SortColumn::Domain,
+        SortColumn::Type,
+        SortColumn::Resolver,
+        SortColumn::Source,
+        SortColumn::Status,
+        SortColumn::Latency,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            SortColumn::Time => "Time",
+            SortColumn::Domain => "Domain",
+            SortColumn::Type => "Type",
+            SortColumn::Resolver => "Resolver",
+            SortColumn::Source => "Source",
+            SortColumn::Status => "Status",
+            SortColumn::Latency => "Latency",
+        }
+    }
+
+    fn next(self) -> Self {
+        let i = SortColumn::ALL.iter().position(|column| *column == self);
+        SortColumn::ALL[i.map_or(0, |i| (i + 1) % SortColumn::ALL.len())]
+    }
+
+    // Newest and slowest first; everything else alphabetically
+    fn descending_by_default(self) -> bool {
+        matches!(self, SortColumn::Time | SortColumn::Latency)
+    }
+
+    fn compare(self, a: &LoggedQuery, b: &LoggedQuery) -> Ordering {
+        match self {
+            SortColumn::Time => arrival(a).cmp(&arrival(b)),
+            SortColumn::Domain => a.domain.cmp(&b.domain),
+            SortColumn::Type => a.query_type.cmp(&b.query_type),
+            SortColumn::Resolver => a.resolver().cmp(&b.resolver()),
+            SortColumn::Source => a.source.cmp(&b.source),
+            SortColumn::Status => status(&a.outcome).cmp(status(&b.outcome)),
+            SortColumn::Latency => latency(&a.outcome).cmp(&latency(&b.outcome)),
+        }
+    }
+}
+
+// Order of arrival, for queries captured at the same time too
+fn arrival(query: &LoggedQuery) -> (SystemTime, u64) {
+    (query.timestamp, query.id)
+}
+
+// The response code, or what became of a query without a response
+fn status(outcome: &QueryOutcome) -> &str {
+    match outcome {
+        QueryOutcome::Pending => "pending",
+        QueryOutcome::Answered { rcode, .. } => rcode,
+        QueryOutcome::TimedOut => "timed out",
+        QueryOutcome::Unsolicited => "response",
+    }
+}
+
+// Sort key for response latency: timeouts count as slower than any response,
+// and queries still waiting, or responses without a query, as faster
+fn latency(outcome: &QueryOutcome) -> Option<Duration> {
+    match outcome {
+        QueryOutcome::Pending | QueryOutcome::Unsolicited => None,
+        QueryOutcome::Answered { rtt, .. } => Some(*rtt),
+        QueryOutcome::TimedOut => Some(Duration::MAX),
+    }
+}
+
+// Selection, sorting and scrolling of the query log
+pub struct QueryLog {
+    state: TableState,
+    // Id of the selected query, so it stays selected as new queries arrive
+    selected: Option<u64>,
+    sort: SortColumn,
+    descending: bool,
+    // Whether the detail pane is open for the selected query
+    detail: bool,
+    // Lines the detail pane is scrolled down by, clamped to its content when drawn
+    detail_scroll: u16,
+    // Lines that fit in the detail pane when it was last drawn, for paging
+    detail_page: u16,
+    // Rows that fit in the table when it was last drawn, for paging
+    page: usize,
+}
+
+impl Default for QueryLog {
+    fn default() -> Self {
+        Self {
+            state: TableState::default(),
+            selected: None,
+            sort: SortColumn::Time,
+            descending: true,
+            detail: false,
+            detail_scroll: 0,
+            detail_page: 10,
+            page: 10,
+        }
+    }
+}
+
+impl QueryLog {
+    // The queries passing the filters, in display order
+    fn rows<'a>(&self, data: &'a DnsTrafficData) -> Vec<&'a LoggedQuery> {
+        let mut rows: Vec<&LoggedQuery> = data
+            .recent_queries
+            .iter()
+            .filter(|query| data.shows_logged(query))
+            .collect();
+        rows.sort_by(|a, b| {
+            let ordering = self.sort.compare(a, b);
+            let ordering = if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            };
+            // Ties newest first, so rows don't jump around
+            ordering.then_with(|| arrival(b).cmp(&arrival(a)))
+        });
+        rows
+    }
+
+    fn position(&self, rows: &[&LoggedQuery]) -> Option<usize> {
+        self.selected
+            .and_then(|id| rows.iter().position(|query| query.id == id))
+    }
+
+    // Move the selection by `delta` rows, stopping at either end
+    fn move_selection(&mut self, data: &DnsTrafficData, delta: isize) {
+        let rows = self.rows(data);
+        if rows.is_empty() {
+            return;
+        }
+        let target = match self.position(&rows) {
+            Some(current) => (current as isize + delta).clamp(0, rows.len() as isize - 1) as usize,
+            None => 0,
+        };
+        self.selected = Some(rows[target].id);
+    }
+
+    fn select_edge(&mut self, data: &DnsTrafficData, last: bool) {
+        let rows = self.rows(data);
+        let query = if last { rows.last() } else { rows.first() };
+        self.selected = query.map(|query| query.id);
+    }
+
+    // Handle a key press meant for the query log. Returns false for keys it
+    // doesn't use.
+    pub fn handle_key(&mut self, data: &DnsTrafficData, code: KeyCode) -> bool {
+        let page = self.page.max(1) as isize;
+        let selected = self.selected;
+        match code {
+            // While the detail pane is open, J/K and PgUp/PgDn scroll it
+            KeyCode::Char('J') if self.detail => self.scroll_detail(1),
+            KeyCode::Char('K') if self.detail => self.scroll_detail(-1),
+            KeyCode::PageDown if self.detail => self.scroll_detail(self.detail_page.max(1) as i32),
+            KeyCode::PageUp if self.detail => self.scroll_detail(-(self.detail_page.max(1) as i32)),
+            KeyCode::Char('j') | KeyCode::Down => self.move_selection(data, 1),
+            KeyCode::Char('k') | KeyCode::Up => self.move_selection(data, -1),
+            KeyCode::PageDown => self.move_selection(data, page),
+            KeyCode::PageUp => self.move_selection(data, -page),
+            KeyCode::Home | KeyCode::Char('g') => self.select_edge(data, false),
+            KeyCode::End | KeyCode::Char('G') => self.select_edge(data, true),
+            KeyCode::Enter => {
+                if self.selected.is_none() {
+                    self.select_edge(data, false);
+                }
+                self.detail = !self.detail && self.selected.is_some();
+            }
+            KeyCode::Char('s') => {
+                self.sort = self.sort.next();
+                self.descending = self.sort.descending_by_default();
+            }
+            KeyCode::Char('r') => self.descending = !self.descending,
+            KeyCode::Esc if self.detail => self.detail = false,
+            _ => return false,
+        }
+        // Another query's details start from the top
+        if self.selected != selected || !self.detail {
+            self.detail_scroll = 0;
+        }
+        true
+    }
+
+    // Scroll the detail pane by `delta` lines; render stops it at the end
+    fn scroll_detail(&mut self, delta: i32) {
+        self.detail_scroll = (self.detail_scroll as i32 + delta).clamp(0, u16::MAX as i32) as u16;
+    }
+
+    // Keys for the status line
+    pub fn help(&self) -> &'static str {
+        if self.detail {
+            "  [j/k] previous/next  [J/K PgUp/PgDn] scroll  [Enter/Esc] close details"
+        } else {
+            "  [j/k PgUp/PgDn Home/End] move  [Enter] details  [s] sort  [r] reverse"
+        }
+    }
+}
+
+// Draw the query log over `area`, with the detail pane below it when open
+pub fn render(f: &mut Frame, area: Rect, data: &DnsTrafficData, log: &mut QueryLog) {
+    let rows = log.rows(data);
+
+    // Keep the selection in place if its query was pruned or filtered out
+    let position = match log.position(&rows) {
+        Some(position) => Some(position),
+        None if log.selected.is_some() && !rows.is_empty() => {
+            let position = log.state.selected().unwrap_or(0).min(rows.len() - 1);
+            log.selected = Some(rows[position].id);
+            Some(position)
+        }
+        None => None,
+    };
+    log.state.select(position);
+    if position.is_none() {
+        log.detail = false;
+    }
+
+    let chunks = if log.detail {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(area)
+    } else {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(100)])
+            .split(area)
+    };
+    // Borders and the header row
+    log.page = chunks[0].height.saturating_sub(3) as usize;
+
+    let header = Row::new(SortColumn::ALL.iter().map(|column| {
+        let arrow = match (*column == log.sort, log.descending) {
+            (true, true) => " ▼",
+            (true, false) => " ▲",
+            (false, _) => "",
+        };
+        Cell::from(format!("{}{}", column.title(), arrow))
+    }))
+    .style(
+        Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+    );
+
+    let table_rows: Vec<Row> = rows
+        .iter()
+        .map(|query| {
+            let latency = match &query.outcome {
+                QueryOutcome::Answered { rtt, .. } => {
+                    format!("{:.1}ms", rtt.as_secs_f64() * 1000.0)
+                }
+                _ => String::new(),
+            };
+            let color = match &query.outcome {
+                QueryOutcome::Pending => Color::Gray,
+                QueryOutcome::Unsolicited => Color::Cyan,
+                QueryOutcome::Answered { rcode, .. } if rcode == "NOERROR" => Color::White,
+                QueryOutcome::Answered { .. } | QueryOutcome::TimedOut => Color::Red,
+            };
+            Row::new(vec![
+                format_time(query.timestamp),
+                query.domain.clone(),
+                query.query_type.clone(),
+                query.resolver(),
+                query.source.clone(),
+                status(&query.outcome).to_string(),
+                latency,
+            ])
+            .style(Style::default().fg(color))
+        })
+        .collect();
+
+    let table = Table::new(
+        table_rows,
+        [
+            Constraint::Length(8),
+            Constraint::Fill(1),
+            Constraint::Length(8),
+            Constraint::Length(14),
+            Constraint::Length(24),
+            Constraint::Length(10),
+            Constraint::Length(10),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .title(Span::styled(
+                format!(
+                    "Query Log{} ({} of the last {} queries)",
+                    data.filter_indicator(),
+                    rows.len(),
+                    data.recent_queries.len()
+                ),
+                Style::default()
+                    .fg(Color::Magenta)
+                    .add_modifier(Modifier::BOLD),
+            ))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Gray)),
+    )
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+    .highlight_symbol(">> ");
+
+    f.render_stateful_widget(table, chunks[0], &mut log.state);
+
+    if log.detail
+        && let Some(query) = position.map(|position| rows[position])
+    {
+        let block = Block::default()
+            .title(Span::styled(
+                "Query Details",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Gray));
+        let inner = block.inner(chunks[1]);
+        let details = Paragraph::new(detail_lines(query)).wrap(Wrap { trim: false });
+
+        // Stop scrolling once the last line is at the bottom of the pane
+        let overflow = details
+            .line_count(inner.width)
+            .saturating_sub(inner.height as usize);
+        log.detail_scroll = log
+            .detail_scroll
+            .min(overflow.min(u16::MAX as usize) as u16);
+        log.detail_page = inner.height;
+
+        f.render_widget(
+            details.scroll((log.detail_scroll, 0)).block(block),
+            chunks[1],
+        );
+    }
+}
+
+fn heading(text: String) -> Line<'static> {
+    Line::from(Span::styled(
+        text,
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD),
+    ))
+}
+
+fn field(name: &str, value: String) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(format!("  {}: ", name), Style::default().fg(Color::Gray)),
+        Span::styled(value, Style::default().fg(Color::White)),
+    ])
+}
+
+// Everything known about a query and its response
+fn detail_lines(query: &LoggedQuery) -> Vec<Line<'static>> {
+    let packet = &query.packet;
+    let kind = match query.outcome {
+        QueryOutcome::Unsolicited => "Response",
+        _ => "Query",
+    };
+    let mut lines = vec![heading(format!(
+        "{} for {} ({}) at {}",
+        kind,
+        query.domain,
+        query.query_type,
+        format_time(query.timestamp)
+    ))];
+    lines.push(field(
+        "Sent",
+        format!(
+            "from {} to {} over {} {}",
+            SocketAddr::new(packet.source, packet.source_port),
+            SocketAddr::new(packet.destination, packet.destination_port),
+            packet.protocol,
+            packet.transport.as_str()
+        ),
+    ));
+    let mut resolver = query.resolver();
+    if query.bypass {
+        resolver.push_str(" (bypassing the system resolvers)");
+    }
+    lines.push(field("Resolver", resolver));
+    if let Some(process) = &query.process {
+        lines.push(field("Process", process.to_string()));
+    }
+    if let Some(interface) = &query.interface {
+        lines.push(field("Interface", interface.clone()));
+    }
+    message_lines(&mut lines, packet);
+
+    match &query.outcome {
+        QueryOutcome::Answered { rtt, response, .. } => {
+            lines.push(Line::default());
+            lines.push(heading(format!(
+                "Response {} after {:.1}ms",
+                response.rcode_name(),
+                rtt.as_secs_f64() * 1000.0
+            )));
+            lines.push(field(
+                "From",
+                SocketAddr::new(response.source, response.source_port).to_string(),
+            ));
+            message_lines(&mut lines, response);
+        }
+        QueryOutcome::TimedOut => {
+            lines.push(Line::default());
+            lines.push(heading("No response: timed out".to_string()));
+        }
+        QueryOutcome::Pending => {
+            lines.push(Line::default());
+            lines.push(heading("Awaiting a response".to_string()));
+        }
+        // The logged message is the response itself
+        QueryOutcome::Unsolicited => {}
+    }
+    lines
+}
+
+// The header and every section of a message
+fn message_lines(lines: &mut Vec<Line<'static>>, packet: &DnsPacket) {
+    let header = &packet.header;
+    lines.push(field(
+        "Header",
+        format!(
+            "id {:#06x}, opcode {:?}, flags [{}], rcode {}",
+            header.id,
+            header.opcode,
+            header.flags(),
+            packet.rcode_name()
+        ),
+    ));
+    if packet.checksum == ChecksumStatus::Invalid {
+        lines.push(field(
+            "Warning",
+            "bad checksum (possibly offloaded to the NIC)".to_string(),
+        ));
+    }
+
+    if !packet.questions.is_empty() {
+        lines.push(field("Questions", String::new()));
+        for question in &packet.questions {
+            let unicast = if question.unicast_response {
+                " (QU)"
+            } else {
+                ""
+            };
+            lines.push(Line::from(format!(
+                "    {} {}{}",
+                question.name, question.query_type, unicast
+            )));
+        }
+    }
+    // A response without answers is worth pointing out
+    section_lines(lines, "Answers", &packet.answers, header.is_response);
+    section_lines(lines, "Authority", &packet.authorities, false);
+    section_lines(lines, "Additional", &packet.additionals, false);
+
+    if let Some(edns) = &packet.edns {
+        lines.push(field(
+            "EDNS",
+            format!(
+                "version {}, udp payload {}{}",
+                edns.version,
+                edns.udp_payload_size,
+                if edns.dnssec_ok { ", DNSSEC OK" } else { "" }
+            ),
+        ));
+        for option in &edns.options {
+            lines.push(Line::from(format!("    {}", option)));
+        }
+    }
+}
+
+fn section_lines(
+    lines: &mut Vec<Line<'static>>,
+    name: &str,
+    records: &[DnsRecord],
+    show_empty: bool,
+) {
+    if records.is_empty() {
+        if show_empty {
+            lines.push(field(name, "none".to_string()));
+        }
+        return;
+    }
+    lines.push(field(name, String::new()));
+    for record in records {
+        lines.push(Line::from(format!(
+            "    {} {} {} (TTL {}s){}",
+            record.name,
+            record.data.type_name(),
+            record.data,
+            record.ttl,
+            if record.cache_flush {
+                ", cache flush"
+            } else {
+                ""
+            }
+        )));
+    }
+}
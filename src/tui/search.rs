@@ -0,0 +1,89 @@
+// Domains hidden by the noisy suffix toggle: reverse lookups, DNS-SD service
+// browsing and the captive portal checks operating systems and browsers make
+const NOISY_SUFFIXES: [&str; 9] = [
+    "in-addr.arpa",
+    "ip6.arpa",
+    "_tcp.local",
+    "_udp.local",
+    "captive.apple.com",
+    "connectivitycheck.gstatic.com",
+    "detectportal.firefox.com",
+    "msftconnecttest.com",
+    "msftncsi.com",
+];
+
+// Whether `domain` is, or is under, one of the noisy suffixes
+pub fn is_noisy(domain: &str) -> bool {
+    let domain = domain.trim_end_matches('.').to_lowercase();
+    NOISY_SUFFIXES.iter().any(|suffix| {
+        domain == *suffix
+            || domain
+                .strip_suffix(suffix)
+                .is_some_and(|rest| rest.ends_with('.'))
+    })
+}
+
+// Text typed after `/`, narrowing the panels as it's typed. Every word must
+// appear in one of a query's fields; a word starting with `-` must appear in
+// none of them, e.g. `amazon -cdn`.
+#[derive(Default)]
+pub struct Search {
+    text: String,
+    // Whether the prompt is open and taking key presses
+    editing: bool,
+}
+
+impl Search {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.text.trim().is_empty()
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.editing
+    }
+
+    // Open the prompt, keeping any search to refine it
+    pub fn start(&mut self) {
+        self.editing = true;
+    }
+
+    // Close the prompt, keeping the search
+    pub fn finish(&mut self) {
+        self.editing = false;
+    }
+
+    // Close the prompt and stop searching
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.editing = false;
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.text.push(c);
+    }
+
+    pub fn pop(&mut self) {
+        self.text.pop();
+    }
+
+    // Whether a query with these field values (qname, provider, source,
+    // query type) passes the search
+    pub fn matches(&self, fields: &[&str]) -> bool {
+        let fields: Vec<String> = fields.iter().map(|field| field.to_lowercase()).collect();
+        let contains = |term: &str| fields.iter().any(|field| field.contains(term));
+
+        self.text
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .all(|term| match term.strip_prefix('-') {
+                // A lone "-" is still being typed
+                Some("") => true,
+                Some(excluded) => !contains(excluded),
+                None => contains(&term),
+            })
+    }
+}
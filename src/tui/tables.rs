@@ -0,0 +1,553 @@
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style},
+    text::Span,
+    widgets::{Block, Borders, Cell, Row, Table, TableState},
+    Frame,
+};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap, HashSet},
+    hash::Hash,
+    time::{Duration, SystemTime},
+};
+
+use super::{format_time, resolver_name, DnsTrafficData, Outcomes, QueryKey};
+
+// The tabs that sum the traffic into a table
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TableKind {
+    Domains,
+    Clients,
+    Resolvers,
+    Errors,
+}
+
+// A table column and the width it's given
+struct Column {
+    title: &'static str,
+    width: Constraint,
+    // Whether `s` sorts it highest first, as for counts and times
+    descending: bool,
+}
+
+const fn column(title: &'static str, width: Constraint, descending: bool) -> Column {
+    Column {
+        title,
+        width,
+        descending,
+    }
+}
+
+const DOMAIN_COLUMNS: [Column; 7] = [
+    column("Domain", Constraint::Fill(1), false),
+    column("Queries", Constraint::Length(9), true),
+    column("Types", Constraint::Length(20), false),
+    column("Clients", Constraint::Length(9), true),
+    column("Failed", Constraint::Length(9), true),
+    column("First seen", Constraint::Length(12), true),
+    column("Last seen", Constraint::Length(12), true),
+];
+
+const CLIENT_COLUMNS: [Column; 8] = [
+    column("Client", Constraint::Length(26), false),
+    column("Queries", Constraint::Length(9), true),
+    column("Domains", Constraint::Length(9), true),
+    column("Failed", Constraint::Length(9), true),
+    column("Resolvers", Constraint::Fill(1), false),
+    column("Processes", Constraint::Fill(1), false),
+    column("First seen", Constraint::Length(12), true),
+    column("Last seen", Constraint::Length(12), true),
+];
+
+const RESOLVER_COLUMNS: [Column; 10] = [
+    column("Resolver", Constraint::Fill(1), false),
+    column("Queries", Constraint::Length(9), true),
+    column("Share", Constraint::Length(8), true),
+    column("Clients", Constraint::Length(9), true),
+    column("Avg", Constraint::Length(10), true),
+    column("Min", Constraint::Length(10), true),
+    column("Max", Constraint::Length(10), true),
+    column("Errors", Constraint::Length(8), true),
+    column("Timeouts", Constraint::Length(10), true),
+    column("Error rate", Constraint::Length(12), true),
+];
+
+const ERROR_COLUMNS: [Column; 7] = [
+    column("Domain", Constraint::Fill(1), false),
+    column("Type", Constraint::Length(8), false),
+    column("Resolver", Constraint::Length(14), false),
+    column("Status", Constraint::Length(10), false),
+    column("Count", Constraint::Length(8), true),
+    column("Clients", Constraint::Length(9), true),
+    column("Last seen", Constraint::Length(12), true),
+];
+
+impl TableKind {
+    pub fn title(self) -> &'static str {
+        match self {
+            TableKind::Domains => "Domains",
+            TableKind::Clients => "Clients",
+            TableKind::Resolvers => "Resolvers",
+            TableKind::Errors => "Errors",
+        }
+    }
+
+    fn columns(self) -> &'static [Column] {
+        match self {
+            TableKind::Domains => &DOMAIN_COLUMNS,
+            TableKind::Clients => &CLIENT_COLUMNS,
+            TableKind::Resolvers => &RESOLVER_COLUMNS,
+            TableKind::Errors => &ERROR_COLUMNS,
+        }
+    }
+
+    // The column sorted by at first: how many queries or errors there were
+    fn default_sort(self) -> usize {
+        match self {
+            TableKind::Errors => 4,
+            _ => 1,
+        }
+    }
+
+    // The shown traffic summed into rows, unsorted
+    fn rows(self, data: &DnsTrafficData) -> Vec<StatRow> {
+        match self {
+            TableKind::Domains => domain_rows(data),
+            TableKind::Clients => client_rows(data),
+            TableKind::Resolvers => resolver_rows(data),
+            TableKind::Errors => error_rows(data),
+        }
+    }
+}
+
+// What a column is sorted on
+#[derive(PartialEq, PartialOrd)]
+enum SortKey {
+    Text(String),
+    Number(f64),
+    Time(SystemTime),
+    // Resolvers without a response sort below any latency
+    Latency(Option<Duration>),
+}
+
+struct StatCell {
+    text: String,
+    key: SortKey,
+}
+
+impl StatCell {
+    fn text(text: String) -> Self {
+        Self {
+            key: SortKey::Text(text.to_lowercase()),
+            text,
+        }
+    }
+
+    fn count(count: usize) -> Self {
+        Self {
+            text: count.to_string(),
+            key: SortKey::Number(count as f64),
+        }
+    }
+
+    // `part` as a share of `whole`, e.g. "12.5%"
+    fn percent(part: u32, whole: u32) -> Self {
+        let ratio = if whole > 0 {
+            part as f64 / whole as f64
+        } else {
+            0.0
+        };
+        Self {
+            text: format!("{:.1}%", ratio * 100.0),
+            key: SortKey::Number(ratio),
+        }
+    }
+
+    fn time(time: SystemTime) -> Self {
+        Self {
+            text: format_time(time),
+            key: SortKey::Time(time),
+        }
+    }
+
+    fn latency(latency: Option<Duration>) -> Self {
+        Self {
+            text: latency
+                .map(|latency| format!("{:.1}ms", latency.as_secs_f64() * 1000.0))
+                .unwrap_or_default(),
+            key: SortKey::Latency(latency),
+        }
+    }
+}
+
+struct StatRow {
+    // Identifies the row while it moves around the table
+    id: String,
+    cells: Vec<StatCell>,
+    color: Color,
+}
+
+// Queries summed over the query keys sharing a label
+#[derive(Default)]
+struct Group<'a> {
+    queries: u32,
+    domains: HashSet<&'a str>,
+    types: BTreeSet<&'a str>,
+    clients: HashSet<&'a str>,
+    resolvers: BTreeSet<String>,
+    processes: BTreeSet<&'a str>,
+    // When the first and latest of the queries were sent
+    seen: Option<(SystemTime, SystemTime)>,
+}
+
+impl Group<'_> {
+    fn first_seen(&self) -> StatCell {
+        self.seen.map_or_else(
+            || StatCell::text(String::new()),
+            |(first, _)| StatCell::time(first),
+        )
+    }
+
+    fn last_seen(&self) -> StatCell {
+        self.seen.map_or_else(
+            || StatCell::text(String::new()),
+            |(_, last)| StatCell::time(last),
+        )
+    }
+}
+
+// Sum the shown queries by `label`. Queries without a label aren't counted.
+fn groups<'a, K, F>(data: &'a DnsTrafficData, label: F) -> HashMap<K, Group<'a>>
+where
+    K: Hash + Eq,
+    F: Fn(&QueryKey) -> Option<K>,
+{
+    let mut groups: HashMap<K, Group> = HashMap::new();
+    for (key, count) in &data.query_counts {
+        if !data.shows(key) {
+            continue;
+        }
+        let Some(label) = label(key) else {
+            continue;
+        };
+        let group = groups.entry(label).or_default();
+        group.queries += count.queries;
+        group.domains.insert(&key.domain);
+        group.types.insert(&key.query_type);
+        group.clients.insert(&key.source);
+        group
+            .resolvers
+            .insert(resolver_name(key.protocol, key.provider));
+        if let Some(process) = &key.process {
+            group.processes.insert(process);
+        }
+        group.seen = Some(match group.seen {
+            Some((first, last)) => (first.min(count.first_seen), last.max(count.last_seen)),
+            None => (count.first_seen, count.last_seen),
+        });
+    }
+    groups
+}
+
+fn join(items: impl IntoIterator<Item = impl AsRef<str>>) -> String {
+    items
+        .into_iter()
+        .map(|item| item.as_ref().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn domain_rows(data: &DnsTrafficData) -> Vec<StatRow> {
+    let outcomes = data.outcomes_by(|key| Some(key.domain.clone()));
+    groups(data, |key| Some(key.domain.clone()))
+        .into_iter()
+        .map(|(domain, group)| {
+            let failed = outcomes.get(&domain).map_or(0, Outcomes::failures);
+            StatRow {
+                cells: vec![
+                    StatCell::text(domain.clone()),
+                    StatCell::count(group.queries as usize),
+                    StatCell::text(join(&group.types)),
+                    StatCell::count(group.clients.len()),
+                    StatCell::count(failed as usize),
+                    group.first_seen(),
+                    group.last_seen(),
+                ],
+                id: domain,
+                color: Color::White,
+            }
+        })
+        .collect()
+}
+
+fn client_rows(data: &DnsTrafficData) -> Vec<StatRow> {
+    let outcomes = data.outcomes_by(|key| Some(key.source.clone()));
+    groups(data, |key| Some(key.source.clone()))
+        .into_iter()
+        .map(|(client, group)| {
+            let failed = outcomes.get(&client).map_or(0, Outcomes::failures);
+            StatRow {
+                cells: vec![
+                    StatCell::text(client.clone()),
+                    StatCell::count(group.queries as usize),
+                    StatCell::count(group.domains.len()),
+                    StatCell::count(failed as usize),
+                    StatCell::text(join(&group.resolvers)),
+                    StatCell::text(join(&group.processes)),
+                    group.first_seen(),
+                    group.last_seen(),
+                ],
+                id: client,
+                color: Color::White,
+            }
+        })
+        .collect()
+}
+
+// Plain DNS resolvers only, as the link-local protocols have none
+fn resolver_rows(data: &DnsTrafficData) -> Vec<StatRow> {
+    let outcomes = data.outcomes_by(|key| key.protocol.is_unicast().then_some(key.provider));
+    let groups = groups(data, |key| {
+        key.protocol.is_unicast().then_some(key.provider)
+    });
+    let total: u32 = groups.values().map(|group| group.queries).sum();
+    groups
+        .into_iter()
+        .map(|(provider, group)| {
+            let none = Outcomes::default();
+            let outcomes = outcomes.get(&provider).unwrap_or(&none);
+            let latency = &outcomes.latency;
+            StatRow {
+                id: provider.as_str().to_string(),
+                cells: vec![
+                    StatCell::text(provider.as_str().to_string()),
+                    StatCell::count(group.queries as usize),
+                    StatCell::percent(group.queries, total),
+                    StatCell::count(group.clients.len()),
+                    StatCell::latency(latency.average()),
+                    StatCell::latency(latency.min),
+                    StatCell::latency((latency.count > 0).then_some(latency.max)),
+                    StatCell::count(outcomes.errors as usize),
+                    StatCell::count(outcomes.timeouts as usize),
+                    StatCell::percent(outcomes.failures(), group.queries),
+                ],
+                color: provider.color(),
+            }
+        })
+        .collect()
+}
+
+// Failed queries sharing a domain, type, resolver and status
+struct ErrorGroup<'a> {
+    count: u32,
+    clients: HashSet<&'a str>,
+    last_seen: SystemTime,
+}
+
+// Responses other than NOERROR, and timeouts, by query and status
+fn error_rows(data: &DnsTrafficData) -> Vec<StatRow> {
+    let mut errors: HashMap<(&str, &str, String, &str), ErrorGroup> = HashMap::new();
+    for (key, count) in &data.outcome_counts {
+        let status = key.rcode.as_deref().unwrap_or("timed out");
+        if status == "NOERROR" || !data.shows_outcome(key) {
+            continue;
+        }
+        let resolver = resolver_name(key.protocol, key.provider);
+        let group = errors
+            .entry((&key.domain, &key.query_type, resolver, status))
+            .or_insert_with(|| ErrorGroup {
+                count: 0,
+                clients: HashSet::new(),
+                last_seen: count.last_seen,
+            });
+        group.count += count.count;
+        group.clients.insert(&key.source);
+        group.last_seen = group.last_seen.max(count.last_seen);
+    }
+
+    errors
+        .into_iter()
+        .map(|((domain, query_type, resolver, status), group)| StatRow {
+            id: format!("{} {} {} {}", domain, query_type, resolver, status),
+            cells: vec![
+                StatCell::text(domain.to_string()),
+                StatCell::text(query_type.to_string()),
+                StatCell::text(resolver),
+                StatCell::text(status.to_string()),
+                StatCell::count(group.count as usize),
+                StatCell::count(group.clients.len()),
+                StatCell::time(group.last_seen),
+            ],
+            // Names that don't exist are often expected, e.g. search domains
+            // being tried; failures to resolve aren't
+            color: if status == "NXDOMAIN" {
+                Color::Yellow
+            } else {
+                Color::Red
+            },
+        })
+        .collect()
+}
+
+// Selection, sorting and scrolling of one of the tables
+pub struct StatsTable {
+    kind: TableKind,
+    state: TableState,
+    // Id of the selected row, so it stays selected as the counts change
+    selected: Option<String>,
+    sort: usize,
+    descending: bool,
+    // Rows that fit in the table when it was last drawn, for paging
+    page: usize,
+}
+
+impl StatsTable {
+    pub fn new(kind: TableKind) -> Self {
+        Self {
+            kind,
+            state: TableState::default(),
+            selected: None,
+            sort: kind.default_sort(),
+            descending: true,
+            page: 10,
+        }
+    }
+
+    // The rows passing the filters, in display order
+    fn rows(&self, data: &DnsTrafficData) -> Vec<StatRow> {
+        let mut rows = self.kind.rows(data);
+        rows.sort_by(|a, b| {
+            let ordering = a.cells[self.sort]
+                .key
+                .partial_cmp(&b.cells[self.sort].key)
+                .unwrap_or(Ordering::Equal);
+            let ordering = if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            };
+            // Ties by name, so rows don't jump around
+            ordering.then_with(|| a.id.cmp(&b.id))
+        });
+        rows
+    }
+
+    fn position(&self, rows: &[StatRow]) -> Option<usize> {
+        self.selected
+            .as_ref()
+            .and_then(|id| rows.iter().position(|row| row.id == *id))
+    }
+
+    // Move the selection by `delta` rows, stopping at either end
+    fn move_selection(&mut self, data: &DnsTrafficData, delta: isize) {
+        let rows = self.rows(data);
+        if rows.is_empty() {
+            return;
+        }
+        let target = match self.position(&rows) {
+            Some(current) => (current as isize + delta).clamp(0, rows.len() as isize - 1) as usize,
+            None => 0,
+        };
+        self.selected = Some(rows[target].id.clone());
+    }
+
+    fn select_edge(&mut self, data: &DnsTrafficData, last: bool) {
+        let rows = self.rows(data);
+        let row = if last { rows.last() } else { rows.first() };
+        self.selected = row.map(|row| row.id.clone());
+    }
+
+    // Handle a key press meant for the table. Returns false for keys it
+    // doesn't use.
+    pub fn handle_key(&mut self, data: &DnsTrafficData, code: KeyCode) -> bool {
+        let page = self.page.max(1) as isize;
+        match code {
+            KeyCode::Char('j') | KeyCode::Down => self.move_selection(data, 1),
+            KeyCode::Char('k') | KeyCode::Up => self.move_selection(data, -1),
+            KeyCode::PageDown => self.move_selection(data, page),
+            KeyCode::PageUp => self.move_selection(data, -page),
+            KeyCode::Home | KeyCode::Char('g') => self.select_edge(data, false),
+            KeyCode::End | KeyCode::Char('G') => self.select_edge(data, true),
+            KeyCode::Char('s') => {
+                self.sort = (self.sort + 1) % self.kind.columns().len();
+                self.descending = self.kind.columns()[self.sort].descending;
+            }
+            KeyCode::Char('r') => self.descending = !self.descending,
+            _ => return false,
+        }
+        true
+    }
+
+    // Keys for the status line
+    pub fn help(&self) -> &'static str {
+        "  [j/k PgUp/PgDn Home/End] move  [s] sort  [r] reverse"
+    }
+}
+
+// Draw the table over `area`
+pub fn render(f: &mut Frame, area: Rect, data: &DnsTrafficData, table: &mut StatsTable) {
+    let rows = table.rows(data);
+
+    // Keep the selection in place if its row was filtered out
+    let position = match table.position(&rows) {
+        Some(position) => Some(position),
+        None if table.selected.is_some() && !rows.is_empty() => {
+            let position = table.state.selected().unwrap_or(0).min(rows.len() - 1);
+            table.selected = Some(rows[position].id.clone());
+            Some(position)
+        }
+        None => None,
+    };
+    table.state.select(position);
+    // Borders and the header row
+    table.page = area.height.saturating_sub(3) as usize;
+
+    let columns = table.kind.columns();
+    let header = Row::new(columns.iter().enumerate().map(|(i, column)| {
+        let arrow = match (i == table.sort, table.descending) {
+            (true, true) => " ▼",
+            (true, false) => " ▲",
+            (false, _) => "",
+        };
+        Cell::from(format!("{}{}", column.title, arrow))
+    }))
+    .style(
+        Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+    );
+
+    let title = format!(
+        "{}{} ({})",
+        table.kind.title(),
+        data.filter_indicator(),
+        rows.len()
+    );
+    let table_rows: Vec<Row> = rows
+        .into_iter()
+        .map(|row| {
+            Row::new(row.cells.into_iter().map(|cell| cell.text))
+                .style(Style::default().fg(row.color))
+        })
+        .collect();
+
+    let widget = Table::new(table_rows, columns.iter().map(|column| column.width))
+        .header(header)
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    title,
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Gray)),
+        )
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol(">> ");
+
+    f.render_stateful_widget(widget, area, &mut table.state);
+}
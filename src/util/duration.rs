@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+/// Parse a human friendly duration such as `500ms`, `30s`, `5m` or `1h`.
+/// A bare number is taken as seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", value))?;
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        "d" => number * 86400.0,
+        other => {
+            return Err(format!(
+                "unknown duration unit '{}' (expected ms, s, m, h or d)",
+                other
+            ))
+        }
+    };
+
+    if seconds <= 0.0 {
+        return Err(format!("duration must be positive: '{}'", value));
+    }
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("duration too large: '{}'", value))
+}
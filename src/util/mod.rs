@@ -1 +1,6 @@
+mod duration;
 mod log;
+mod size;
+
+pub use duration::parse_duration;
+pub use size::parse_size;
@@ -0,0 +1,31 @@
+/// Parse a human friendly byte size such as `512K`, `100M` or `2G` (powers of
+/// 1024). A bare number is taken as bytes.
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", value))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        other => {
+            return Err(format!(
+                "unknown size unit '{}' (expected B, K, M or G)",
+                other
+            ))
+        }
+    };
+
+    let bytes = (number * multiplier as f64) as u64;
+    if bytes == 0 {
+        return Err(format!("size must be positive: '{}'", value));
+    }
+    Ok(bytes)
+}